@@ -0,0 +1,23 @@
+    .text
+    .globl _start
+_start:
+    bl target
+    b target
+    b.eq target
+    adrp x0, value
+    add x0, x0, :lo12:value
+    ldr x1, [x0, :lo12:value]
+    movz x2, #:abs_g1:value
+    movk x2, #:abs_g0_nc:value
+    ret
+
+    .section .text.target, "ax"
+    .globl target
+target:
+    ret
+
+    .data
+    .p2align 3
+    .globl value
+value:
+    .quad target
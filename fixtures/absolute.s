@@ -0,0 +1,10 @@
+    .intel_syntax noprefix
+    .set limit, 0x1234
+    .globl answer
+    .set answer, 42
+
+    .text
+    .globl _start
+_start:
+    mov eax, limit
+    ret
@@ -0,0 +1,13 @@
+    .intel_syntax noprefix
+    .text
+    .globl call_sum
+call_sum:
+    mov rdi, qword ptr [rip + array@GOTPCREL]
+    mov esi, 2
+    jmp sum@PLT
+
+    .data
+    .globl array
+array:
+    .int 1
+    .int 2
@@ -0,0 +1,11 @@
+    .intel_syntax noprefix
+    .text
+    .globl bump
+bump:
+    add dword ptr [counter], 1
+    ret
+
+    .bss
+    .globl counter
+counter:
+    .zero 16
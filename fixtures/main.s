@@ -0,0 +1,16 @@
+    .intel_syntax noprefix
+    .text
+    .globl main
+main:
+    sub rsp, 8
+    mov esi, 2
+    mov edi, offset array
+    call sum
+    add rsp, 8
+    ret
+
+    .data
+    .globl array
+array:
+    .int 1
+    .int 2
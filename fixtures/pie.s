@@ -0,0 +1,18 @@
+    .intel_syntax noprefix
+    .text
+    .globl _start
+_start:
+    mov rdi, qword ptr [rip + pointer]
+    mov esi, 2
+    call sum
+    mov edi, eax
+    mov eax, 60
+    syscall
+
+    .data
+    .p2align 3
+pointer:
+    .quad array
+array:
+    .int 1
+    .int 2
@@ -0,0 +1,14 @@
+    .data
+    .globl table
+table:
+    .quad value
+    .quad value - .
+    .long value@SIZE
+    .quad value@SIZE
+
+    .section .rodata
+    .globl value
+    .type value, @object
+    .size value, 12
+value:
+    .zero 12
@@ -0,0 +1,25 @@
+    .text
+    .globl _start
+_start:
+    call target
+.Lpcrel_hi0:
+    auipc a0, %pcrel_hi(value)
+    addi a0, a0, %pcrel_lo(.Lpcrel_hi0)
+    sd a1, %pcrel_lo(.Lpcrel_hi0)(a0)
+    lui a2, %hi(value)
+    addi a2, a2, %lo(value)
+    beq a0, a1, target
+    jal target
+    c.beqz a0, target
+    c.j target
+
+    .section .text.target, "ax"
+    .globl target
+target:
+    ret
+
+    .data
+    .globl value
+value:
+    .quad target
+    .word target - _start
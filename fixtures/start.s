@@ -0,0 +1,8 @@
+    .intel_syntax noprefix
+    .text
+    .globl _start
+_start:
+    call main
+    mov edi, eax
+    mov eax, 60
+    syscall
@@ -0,0 +1,15 @@
+    .intel_syntax noprefix
+    .text
+    .globl sum
+sum:
+    mov eax, 0
+    mov edx, 0
+    jmp .L2
+.L3:
+    movsxd rcx, edx
+    add eax, [rdi + rcx * 4]
+    add edx, 1
+.L2:
+    cmp edx, esi
+    jl .L3
+    rep ret
@@ -0,0 +1,15 @@
+    .intel_syntax noprefix
+    .text
+    .globl _start
+_start:
+    movabs rdi, offset array
+    mov esi, 2
+    call sum
+    mov edi, eax
+    mov eax, 60
+    syscall
+
+    .data
+array:
+    .int 1
+    .int 2
@@ -0,0 +1,308 @@
+use super::{BitRange, FieldLayout, OverflowCheck, RelocationField, RelocationFormula};
+
+/// `imm16` of `MOVZ`/`MOVK`.
+const MOVW_IMM16: &[BitRange] = &[BitRange {
+    from: 0,
+    to: 5,
+    len: 16,
+}];
+/// `immlo` and `immhi` of `ADR`/`ADRP`.
+const ADR_IMM21: &[BitRange] = &[
+    BitRange {
+        from: 0,
+        to: 29,
+        len: 2,
+    },
+    BitRange {
+        from: 2,
+        to: 5,
+        len: 19,
+    },
+];
+/// `imm12` of `ADD` and of loads and stores with an unsigned offset.
+const IMM12: &[BitRange] = &[BitRange {
+    from: 0,
+    to: 10,
+    len: 12,
+}];
+/// `imm14` of `TBZ`/`TBNZ`.
+const IMM14: &[BitRange] = &[BitRange {
+    from: 0,
+    to: 5,
+    len: 14,
+}];
+/// `imm19` of `B.cond`, `CBZ`/`CBNZ` and literal loads.
+const IMM19: &[BitRange] = &[BitRange {
+    from: 0,
+    to: 5,
+    len: 19,
+}];
+/// `imm26` of `B`/`BL`.
+const IMM26: &[BitRange] = &[BitRange {
+    from: 0,
+    to: 0,
+    len: 26,
+}];
+
+/// The static relocation types of the AArch64 ELF ABI.
+///
+/// Most of them patch the immediate of a little-endian instruction; the `_NC`
+/// variants skip the overflow check.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Aarch64RelocationType {
+    /// `R_AARCH64_ABS64`
+    Abs64,
+    /// `R_AARCH64_ABS32`
+    Abs32,
+    /// `R_AARCH64_ABS16`
+    Abs16,
+    /// `R_AARCH64_PREL64`
+    Prel64,
+    /// `R_AARCH64_PREL32`
+    Prel32,
+    /// `R_AARCH64_PREL16`
+    Prel16,
+    /// `R_AARCH64_MOVW_UABS_G0`
+    MovwUabsG0,
+    /// `R_AARCH64_MOVW_UABS_G0_NC`
+    MovwUabsG0Nc,
+    /// `R_AARCH64_MOVW_UABS_G1`
+    MovwUabsG1,
+    /// `R_AARCH64_MOVW_UABS_G1_NC`
+    MovwUabsG1Nc,
+    /// `R_AARCH64_MOVW_UABS_G2`
+    MovwUabsG2,
+    /// `R_AARCH64_MOVW_UABS_G2_NC`
+    MovwUabsG2Nc,
+    /// `R_AARCH64_MOVW_UABS_G3`
+    MovwUabsG3,
+    /// `R_AARCH64_LD_PREL_LO19`
+    LdPrelLo19,
+    /// `R_AARCH64_ADR_PREL_LO21`
+    AdrPrelLo21,
+    /// `R_AARCH64_ADR_PREL_PG_HI21`
+    AdrPrelPgHi21,
+    /// `R_AARCH64_ADR_PREL_PG_HI21_NC`
+    AdrPrelPgHi21Nc,
+    /// `R_AARCH64_ADD_ABS_LO12_NC`
+    AddAbsLo12Nc,
+    /// `R_AARCH64_LDST8_ABS_LO12_NC`
+    Ldst8AbsLo12Nc,
+    /// `R_AARCH64_LDST16_ABS_LO12_NC`
+    Ldst16AbsLo12Nc,
+    /// `R_AARCH64_LDST32_ABS_LO12_NC`
+    Ldst32AbsLo12Nc,
+    /// `R_AARCH64_LDST64_ABS_LO12_NC`
+    Ldst64AbsLo12Nc,
+    /// `R_AARCH64_LDST128_ABS_LO12_NC`
+    Ldst128AbsLo12Nc,
+    /// `R_AARCH64_TSTBR14`
+    Tstbr14,
+    /// `R_AARCH64_CONDBR19`
+    Condbr19,
+    /// `R_AARCH64_JUMP26`
+    Jump26,
+    /// `R_AARCH64_CALL26`
+    Call26,
+}
+impl Aarch64RelocationType {
+    pub fn formula(&self) -> RelocationFormula {
+        use Aarch64RelocationType::*;
+        match self {
+            Abs64 | Abs32 | Abs16 | MovwUabsG0 | MovwUabsG0Nc | MovwUabsG1 | MovwUabsG1Nc
+            | MovwUabsG2 | MovwUabsG2Nc | MovwUabsG3 | AddAbsLo12Nc | Ldst8AbsLo12Nc
+            | Ldst16AbsLo12Nc | Ldst32AbsLo12Nc | Ldst64AbsLo12Nc | Ldst128AbsLo12Nc => {
+                RelocationFormula::Absolute
+            }
+            Prel64 | Prel32 | Prel16 | LdPrelLo19 | AdrPrelLo21 | Tstbr14 | Condbr19 | Jump26
+            | Call26 => RelocationFormula::PcRelative,
+            AdrPrelPgHi21 | AdrPrelPgHi21Nc => RelocationFormula::PagePcRelative,
+        }
+    }
+
+    pub fn field(&self) -> RelocationField {
+        use Aarch64RelocationType::*;
+        let (shift, bits, overflow, alignment, layout) = match self {
+            Abs64 | Prel64 => return RelocationField::word(8, OverflowCheck::None),
+            Abs32 | Prel32 => return RelocationField::word(4, OverflowCheck::SignedOrUnsigned),
+            Abs16 | Prel16 => return RelocationField::word(2, OverflowCheck::SignedOrUnsigned),
+            MovwUabsG0 => (0, 16, OverflowCheck::Unsigned, 1, MOVW_IMM16),
+            MovwUabsG0Nc => (0, 16, OverflowCheck::None, 1, MOVW_IMM16),
+            MovwUabsG1 => (16, 16, OverflowCheck::Unsigned, 1, MOVW_IMM16),
+            MovwUabsG1Nc => (16, 16, OverflowCheck::None, 1, MOVW_IMM16),
+            MovwUabsG2 => (32, 16, OverflowCheck::Unsigned, 1, MOVW_IMM16),
+            MovwUabsG2Nc => (32, 16, OverflowCheck::None, 1, MOVW_IMM16),
+            // The top group holds the rest of a 64-bit value, so it cannot overflow
+            MovwUabsG3 => (48, 16, OverflowCheck::None, 1, MOVW_IMM16),
+            LdPrelLo19 => (2, 19, OverflowCheck::Signed, 4, IMM19),
+            AdrPrelLo21 => (0, 21, OverflowCheck::Signed, 1, ADR_IMM21),
+            AdrPrelPgHi21 => (12, 21, OverflowCheck::Signed, 1, ADR_IMM21),
+            AdrPrelPgHi21Nc => (12, 21, OverflowCheck::None, 1, ADR_IMM21),
+            AddAbsLo12Nc | Ldst8AbsLo12Nc => (0, 12, OverflowCheck::None, 1, IMM12),
+            // The offset of a load or store is scaled by the access size
+            Ldst16AbsLo12Nc => (1, 11, OverflowCheck::None, 2, IMM12),
+            Ldst32AbsLo12Nc => (2, 10, OverflowCheck::None, 4, IMM12),
+            Ldst64AbsLo12Nc => (3, 9, OverflowCheck::None, 8, IMM12),
+            Ldst128AbsLo12Nc => (4, 8, OverflowCheck::None, 16, IMM12),
+            Tstbr14 => (2, 14, OverflowCheck::Signed, 4, IMM14),
+            Condbr19 => (2, 19, OverflowCheck::Signed, 4, IMM19),
+            Jump26 | Call26 => (2, 26, OverflowCheck::Signed, 4, IMM26),
+        };
+        RelocationField {
+            width: 4,
+            shift,
+            bits,
+            overflow,
+            alignment,
+            layout: FieldLayout::Bits(layout),
+        }
+    }
+
+    pub fn is_branch(&self) -> bool {
+        use Aarch64RelocationType::*;
+        matches!(self, Tstbr14 | Condbr19 | Jump26 | Call26)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        core::models::Architecture,
+        elf::read_relocatable_object,
+        in_memory::{
+            link,
+            models::{Endianness, InMemoryLoadableSectionTable, LinkConfig},
+            LinkInput,
+        },
+    };
+
+    use super::*;
+    use Aarch64RelocationType::*;
+
+    const AARCH64_O: &[u8] = include_bytes!("../../../fixtures/aarch64.o");
+    const AARCH64_BE_O: &[u8] = include_bytes!("../../../fixtures/aarch64_be.o");
+
+    /// Stores `value` in the field of `typ` in `instruction`.
+    fn encode(typ: Aarch64RelocationType, instruction: u32, value: i64) -> u32 {
+        let field = typ.field();
+        assert!(field.fits(value as i128), "{typ:?} overflows");
+        field.insert(instruction as usize, value as usize) as u32
+    }
+
+    #[test]
+    fn encode_fields() {
+        // Compared with `llvm-mc -triple=aarch64`; the field of each instruction is
+        // zeroed first
+        // movz x2, #0x1234
+        assert_eq!(encode(MovwUabsG0, 0xd2800002, 0x1234), 0xd2824682);
+        // movz x2, #0x1234, lsl #16
+        assert_eq!(encode(MovwUabsG1, 0xd2a00002, 0x1234_0000), 0xd2a24682);
+        // movk x2, #0xfedc, lsl #48
+        let value = 0xfedc_0000_0000_0000_u64 as i64;
+        assert_eq!(encode(MovwUabsG3, 0xf2e00002, value), 0xf2ffdb82);
+        // adr x0, .+0x12345 and adr x0, .-0x12345
+        assert_eq!(encode(AdrPrelLo21, 0x10000000, 0x12345), 0x30091a20);
+        assert_eq!(encode(AdrPrelLo21, 0x10000000, -0x12345), 0x70f6e5c0);
+        // adrp x0, #0x12345000 and adrp x0, #-0x1000
+        assert_eq!(encode(AdrPrelPgHi21, 0x90000000, 0x12345000), 0xb0091a20);
+        assert_eq!(encode(AdrPrelPgHi21, 0x90000000, -0x1000), 0xf0ffffe0);
+        // add x0, x0, #0x123
+        assert_eq!(encode(AddAbsLo12Nc, 0x91000000, 0x123), 0x91048c00);
+        // ldrh w1, [x0, #0x1fe]
+        assert_eq!(encode(Ldst16AbsLo12Nc, 0x79400001, 0x1fe), 0x7943fc01);
+        // ldr x1, [x0, #0x1f8]
+        assert_eq!(encode(Ldst64AbsLo12Nc, 0xf9400001, 0x1f8), 0xf940fc01);
+        // ldr q1, [x0, #0x1f0]
+        assert_eq!(encode(Ldst128AbsLo12Nc, 0x3dc00001, 0x1f0), 0x3dc07c01);
+        // tbz w0, #3, .+0x100
+        assert_eq!(encode(Tstbr14, 0x36180000, 0x100), 0x36180800);
+        // b.eq .-8
+        assert_eq!(encode(Condbr19, 0x54000000, -8), 0x54ffffc0);
+        // ldr x0, .+0x1000
+        assert_eq!(encode(LdPrelLo19, 0x58000000, 0x1000), 0x58008000);
+        // b .+0x123450
+        assert_eq!(encode(Jump26, 0x14000000, 0x123450), 0x14048d14);
+        // bl .-4
+        assert_eq!(encode(Call26, 0x94000000, -4), 0x97ffffff);
+    }
+
+    #[test]
+    fn overflow() {
+        let fits = |typ: Aarch64RelocationType, value| typ.field().fits(value);
+        assert!(fits(MovwUabsG0, 0xffff) && !fits(MovwUabsG0, 0x1_0000));
+        assert!(fits(MovwUabsG0Nc, 0x1_0000));
+        assert!(fits(MovwUabsG3, -1));
+        assert!(fits(Call26, -0x800_0000) && !fits(Call26, 0x800_0000));
+        assert!(fits(AdrPrelPgHi21, -0x1_0000_0000) && !fits(AdrPrelPgHi21, 0x1_0000_0000));
+    }
+
+    #[test]
+    fn link_aarch64() {
+        let objects = vec![LinkInput::Object(
+            read_relocatable_object("aarch64.o", AARCH64_O).unwrap(),
+        )];
+        let config = LinkConfig {
+            architecture: Architecture::Aarch64,
+            ..Default::default()
+        };
+        let executable = link(objects, &config).unwrap();
+        let section_table = executable.section_table;
+
+        // `target` follows `_start` at 0x400024, `value` is at 0x401028
+        let text = section_table
+            .section_by_name(".text")
+            .unwrap()
+            .data
+            .bytes()
+            .unwrap();
+        assert_eq!(
+            text,
+            &[
+                0x09, 0x00, 0x00, 0x94, // bl target
+                0x08, 0x00, 0x00, 0x14, // b target
+                0xe0, 0x00, 0x00, 0x54, // b.eq target
+                0x00, 0x00, 0x00, 0xb0, // adrp x0, value
+                0x00, 0xa0, 0x00, 0x91, // add x0, x0, :lo12:value
+                0x01, 0x14, 0x40, 0xf9, // ldr x1, [x0, :lo12:value]
+                0x02, 0x08, 0xa0, 0xd2, // movz x2, #:abs_g1:value
+                0x02, 0x05, 0x82, 0xf2, // movk x2, #:abs_g0_nc:value
+                0xc0, 0x03, 0x5f, 0xd6, // ret
+                0xc0, 0x03, 0x5f, 0xd6, // target: ret
+            ]
+        );
+        let data = section_table
+            .section_by_name(".data")
+            .unwrap()
+            .data
+            .bytes()
+            .unwrap();
+        assert_eq!(data, &0x400024_u64.to_le_bytes());
+    }
+
+    #[test]
+    fn link_aarch64_big_endian() {
+        // `aarch64_be.o` is `aarch64.s` assembled for big-endian AArch64
+        let link_with = |name, bytes, endianness| {
+            let objects = vec![LinkInput::Object(
+                read_relocatable_object(name, bytes).unwrap(),
+            )];
+            let config = LinkConfig {
+                endianness,
+                architecture: Architecture::Aarch64,
+                ..Default::default()
+            };
+            link(objects, &config).unwrap().section_table
+        };
+        let little = link_with("aarch64.o", AARCH64_O, Endianness::Little);
+        let big = link_with("aarch64_be.o", AARCH64_BE_O, Endianness::Big);
+
+        // Instructions stay little-endian; only data words follow the byte order
+        let bytes = |section_table: &InMemoryLoadableSectionTable, name| {
+            let section = section_table.section_by_name(name).unwrap();
+            section.data.bytes().unwrap().to_vec()
+        };
+        assert_eq!(bytes(&big, ".text"), bytes(&little, ".text"));
+        assert_eq!(bytes(&big, ".text")[..4], [0x09, 0x00, 0x00, 0x94]);
+        assert_eq!(bytes(&big, ".data"), 0x400024_u64.to_be_bytes());
+    }
+}
@@ -1,7 +1,15 @@
+pub use aarch64::*;
+pub use origin::*;
 pub use relocation::*;
+pub use riscv::*;
 pub use section::*;
 pub use symbol::*;
+pub use x86_64::*;
 
+mod aarch64;
+mod origin;
 mod relocation;
+mod riscv;
 mod section;
 mod symbol;
+mod x86_64;
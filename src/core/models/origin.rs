@@ -0,0 +1,24 @@
+use std::{fmt, sync::Arc};
+
+/// Where a relocation was found in its input object, for diagnostics.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RelocationOrigin {
+    /// The identifier of the input object (e.g. a file path or archive member name).
+    pub object: Arc<str>,
+    /// The name of the input section.
+    pub section: Arc<str>,
+    /// The offset within the input section.
+    pub offset: usize,
+    /// The symbol whose definition encloses the relocation, if any.
+    pub symbol: Option<Arc<str>>,
+}
+impl fmt::Display for RelocationOrigin {
+    /// Formats the origin like `ld`, e.g. `main.o:(.text+0xf)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:({}+{:#x})", self.object, self.section, self.offset)?;
+        if let Some(symbol) = &self.symbol {
+            write!(f, " in `{symbol}`")?;
+        }
+        Ok(())
+    }
+}
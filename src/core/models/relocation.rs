@@ -1,6 +1,9 @@
 use std::ops::Deref;
 
-use super::{section::SectionIndex, SymbolIndex};
+use super::{
+    section::SectionIndex, Aarch64RelocationType, RelocationOrigin, RiscVRelocationType,
+    SymbolIndex, X86_64RelocationType,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Relocation<S>
@@ -17,15 +20,202 @@ where
     pub addend: isize,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum RelocationType {
-    PcRelative,
+    X86_64(X86_64RelocationType),
+    Aarch64(Aarch64RelocationType),
+    RiscV(RiscVRelocationType),
+}
+impl RelocationType {
+    pub fn formula(&self) -> RelocationFormula {
+        match self {
+            RelocationType::X86_64(typ) => typ.formula(),
+            RelocationType::Aarch64(typ) => typ.formula(),
+            RelocationType::RiscV(typ) => typ.formula(),
+        }
+    }
+    pub fn field(&self) -> RelocationField {
+        match self {
+            RelocationType::X86_64(typ) => typ.field(),
+            RelocationType::Aarch64(typ) => typ.field(),
+            RelocationType::RiscV(typ) => typ.field(),
+        }
+    }
+    pub fn architecture(&self) -> Architecture {
+        match self {
+            RelocationType::X86_64(_) => Architecture::X86_64,
+            RelocationType::Aarch64(_) => Architecture::Aarch64,
+            RelocationType::RiscV(_) => Architecture::RiscV,
+        }
+    }
+    /// Whether the relocation only patches a branch to the symbol, as opposed to
+    /// taking its address.
+    pub fn is_branch(&self) -> bool {
+        match self {
+            RelocationType::X86_64(typ) => typ.is_branch(),
+            RelocationType::Aarch64(typ) => typ.is_branch(),
+            RelocationType::RiscV(typ) => typ.is_branch(),
+        }
+    }
+}
+
+/// The instruction set that code and relocations are for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Architecture {
+    X86_64,
+    Aarch64,
+    RiscV,
+}
+
+/// How the relocated value is computed, in the notation of the ELF psABIs:
+/// `S` is the symbol address, `A` the addend, `P` the address of the reference,
+/// `Z` the symbol size, `GOT` the address of the global offset table and `G` the
+/// offset of the symbol's entry in it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RelocationFormula {
+    /// `S + A`
     Absolute,
+    /// `S + A - P`
+    PcRelative,
+    /// `S + A - P` of the high part relocation at the place labeled by the symbol
+    /// (RISC-V `%pcrel_lo`)
+    PairedPcRelative,
+    /// `Page(S + A) - Page(P)`, where `Page` clears the low 12 bits
+    PagePcRelative,
+    /// `Z + A`
+    Size,
+    /// `G + A`
+    GotEntry,
+    /// `G + GOT + A - P`
+    GotEntryPcRelative,
+    /// `S + A - GOT`
+    GotRelative,
+    /// `GOT + A - P`
+    GotPcRelative,
+}
+
+/// Where the relocated value is written.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RelocationField {
+    /// The width in bytes of the patched data.
+    pub width: usize,
+    /// The number of low bits dropped from the value before it is stored.
+    pub shift: u32,
+    /// The number of bits stored, which the shifted value is checked against.
+    pub bits: u32,
+    pub overflow: OverflowCheck,
+    /// The value must be a multiple of this.
+    pub alignment: usize,
+    pub layout: FieldLayout,
+}
+impl RelocationField {
+    /// A whole data word.
+    pub fn word(width: usize, overflow: OverflowCheck) -> Self {
+        Self {
+            width,
+            shift: 0,
+            bits: width as u32 * 8,
+            overflow,
+            alignment: 1,
+            layout: FieldLayout::Whole,
+        }
+    }
+    /// Whether `value` can be stored without losing information.
+    pub fn fits(&self, value: i128) -> bool {
+        let value = match self.layout {
+            FieldLayout::Split { .. } => (value + (1 << (self.shift - 1))) >> self.shift,
+            _ => value >> self.shift,
+        };
+        let signed = -(1 << (self.bits - 1))..=(1 << (self.bits - 1)) - 1;
+        let unsigned = 0..=(1 << self.bits) - 1;
+        match self.overflow {
+            OverflowCheck::None => true,
+            OverflowCheck::Signed => signed.contains(&value),
+            OverflowCheck::Unsigned => unsigned.contains(&value),
+            OverflowCheck::SignedOrUnsigned => signed.contains(&value) || unsigned.contains(&value),
+        }
+    }
+    /// Stores `value` into `data`, the current contents of the field, keeping
+    /// the bits that are not part of the field (e.g. an instruction's opcode).
+    pub fn insert(&self, data: usize, value: usize) -> usize {
+        match self.layout {
+            FieldLayout::Whole => value >> self.shift,
+            FieldLayout::Bits(ranges) => {
+                scatter(data, (value >> self.shift) & ((1 << self.bits) - 1), ranges)
+            }
+            FieldLayout::Split { high, low } => {
+                let high_value = value.wrapping_add(1 << (self.shift - 1)) >> self.shift;
+                scatter(scatter(data, high_value, high), value, low)
+            }
+            FieldLayout::Add => data.wrapping_add(value),
+            FieldLayout::Subtract => data.wrapping_sub(value),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FieldLayout {
+    /// The value replaces the whole field.
+    Whole,
+    /// The value is split into immediate bitfields of an instruction.
+    Bits(&'static [BitRange]),
+    /// The value is rounded to a multiple of `1 << shift` for the `high` bitfields,
+    /// and the signed remainder goes to the `low` ones (e.g. RISC-V `auipc` + `jalr`).
+    Split {
+        high: &'static [BitRange],
+        low: &'static [BitRange],
+    },
+    /// The value is added to the current contents.
+    Add,
+    /// The value is subtracted from the current contents.
+    Subtract,
+}
+
+impl FieldLayout {
+    /// Whether the field is part of an instruction, which AArch64 and RISC-V always
+    /// encode in little-endian order, whatever the byte order of data.
+    pub fn is_instruction(&self) -> bool {
+        matches!(self, FieldLayout::Bits(_) | FieldLayout::Split { .. })
+    }
+}
+
+/// Bits `from..from + len` of the shifted value, stored at bits `to..to + len`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BitRange {
+    pub from: u32,
+    pub to: u32,
+    pub len: u32,
 }
 
-pub struct ResolvingRelocation<S>(pub Relocation<S>)
+fn scatter(data: usize, value: usize, ranges: &[BitRange]) -> usize {
+    ranges.iter().fold(data, |data, range| {
+        let mask = (1 << range.len) - 1;
+        data & !(mask << range.to) | ((value >> range.from) & mask) << range.to
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OverflowCheck {
+    /// The value is truncated to the field.
+    None,
+    /// The value is sign-extended when read back.
+    Signed,
+    /// The value is zero-extended when read back.
+    Unsigned,
+    SignedOrUnsigned,
+}
+
+pub struct ResolvingRelocation<S>(pub Relocation<S>, pub RelocationOrigin)
 where
     S: SectionIndex;
+impl<S> ResolvingRelocation<S>
+where
+    S: SectionIndex,
+{
+    pub fn origin(&self) -> &RelocationOrigin {
+        &self.1
+    }
+}
 impl<S> Deref for ResolvingRelocation<S>
 where
     S: SectionIndex,
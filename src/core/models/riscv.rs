@@ -0,0 +1,436 @@
+use super::{BitRange, FieldLayout, OverflowCheck, RelocationField, RelocationFormula};
+
+/// `imm[31:12]` of U-type instructions (`lui`, `auipc`).
+const U_IMM: &[BitRange] = &[BitRange {
+    from: 0,
+    to: 12,
+    len: 20,
+}];
+/// `imm[11:0]` of I-type instructions (`addi`, loads, `jalr`).
+const I_IMM: &[BitRange] = &[BitRange {
+    from: 0,
+    to: 20,
+    len: 12,
+}];
+/// `imm[11:0]` of S-type instructions (stores).
+const S_IMM: &[BitRange] = &[
+    BitRange {
+        from: 0,
+        to: 7,
+        len: 5,
+    },
+    BitRange {
+        from: 5,
+        to: 25,
+        len: 7,
+    },
+];
+/// `imm[12:1]` of B-type instructions (conditional branches).
+const B_IMM: &[BitRange] = &[
+    BitRange {
+        from: 1,
+        to: 8,
+        len: 4,
+    },
+    BitRange {
+        from: 5,
+        to: 25,
+        len: 6,
+    },
+    BitRange {
+        from: 11,
+        to: 7,
+        len: 1,
+    },
+    BitRange {
+        from: 12,
+        to: 31,
+        len: 1,
+    },
+];
+/// `imm[20:1]` of J-type instructions (`jal`).
+const J_IMM: &[BitRange] = &[
+    BitRange {
+        from: 1,
+        to: 21,
+        len: 10,
+    },
+    BitRange {
+        from: 11,
+        to: 20,
+        len: 1,
+    },
+    BitRange {
+        from: 12,
+        to: 12,
+        len: 8,
+    },
+    BitRange {
+        from: 20,
+        to: 31,
+        len: 1,
+    },
+];
+/// `imm[8:1]` of CB-type instructions (`c.beqz`, `c.bnez`).
+const CB_IMM: &[BitRange] = &[
+    BitRange {
+        from: 1,
+        to: 3,
+        len: 2,
+    },
+    BitRange {
+        from: 3,
+        to: 10,
+        len: 2,
+    },
+    BitRange {
+        from: 5,
+        to: 2,
+        len: 1,
+    },
+    BitRange {
+        from: 6,
+        to: 5,
+        len: 2,
+    },
+    BitRange {
+        from: 8,
+        to: 12,
+        len: 1,
+    },
+];
+/// `imm[11:1]` of CJ-type instructions (`c.j`, `c.jal`).
+const CJ_IMM: &[BitRange] = &[
+    BitRange {
+        from: 1,
+        to: 3,
+        len: 3,
+    },
+    BitRange {
+        from: 4,
+        to: 11,
+        len: 1,
+    },
+    BitRange {
+        from: 5,
+        to: 2,
+        len: 1,
+    },
+    BitRange {
+        from: 6,
+        to: 7,
+        len: 1,
+    },
+    BitRange {
+        from: 7,
+        to: 6,
+        len: 1,
+    },
+    BitRange {
+        from: 8,
+        to: 9,
+        len: 2,
+    },
+    BitRange {
+        from: 10,
+        to: 8,
+        len: 1,
+    },
+    BitRange {
+        from: 11,
+        to: 12,
+        len: 1,
+    },
+];
+/// `imm[11:0]` of the `jalr` following an `auipc`.
+const CALL_LOW_IMM: &[BitRange] = &[BitRange {
+    from: 0,
+    to: 32 + 20,
+    len: 12,
+}];
+
+/// The static relocation types of the RISC-V ELF psABI, for RV64.
+///
+/// `PCREL_LO12_I`/`S` refer to the label of the instruction patched by the
+/// matching `PCREL_HI20` rather than to the final symbol; `ADD`/`SUB` pairs
+/// compute symbol differences in place.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RiscVRelocationType {
+    /// `R_RISCV_32`
+    Direct32,
+    /// `R_RISCV_64`
+    Direct64,
+    /// `R_RISCV_BRANCH`
+    Branch,
+    /// `R_RISCV_JAL`
+    Jal,
+    /// `R_RISCV_CALL`
+    Call,
+    /// `R_RISCV_CALL_PLT`
+    CallPlt,
+    /// `R_RISCV_GOT_HI20`
+    GotHi20,
+    /// `R_RISCV_PCREL_HI20`
+    PcrelHi20,
+    /// `R_RISCV_PCREL_LO12_I`
+    PcrelLo12I,
+    /// `R_RISCV_PCREL_LO12_S`
+    PcrelLo12S,
+    /// `R_RISCV_HI20`
+    Hi20,
+    /// `R_RISCV_LO12_I`
+    Lo12I,
+    /// `R_RISCV_LO12_S`
+    Lo12S,
+    /// `R_RISCV_ADD8`
+    Add8,
+    /// `R_RISCV_ADD16`
+    Add16,
+    /// `R_RISCV_ADD32`
+    Add32,
+    /// `R_RISCV_ADD64`
+    Add64,
+    /// `R_RISCV_SUB8`
+    Sub8,
+    /// `R_RISCV_SUB16`
+    Sub16,
+    /// `R_RISCV_SUB32`
+    Sub32,
+    /// `R_RISCV_SUB64`
+    Sub64,
+    /// `R_RISCV_RVC_BRANCH`
+    RvcBranch,
+    /// `R_RISCV_RVC_JUMP`
+    RvcJump,
+    /// `R_RISCV_SET8`
+    Set8,
+    /// `R_RISCV_SET16`
+    Set16,
+    /// `R_RISCV_SET32`
+    Set32,
+    /// `R_RISCV_32_PCREL`
+    Pcrel32,
+}
+impl RiscVRelocationType {
+    pub fn formula(&self) -> RelocationFormula {
+        use RiscVRelocationType::*;
+        match self {
+            Direct32 | Direct64 | Hi20 | Lo12I | Lo12S | Add8 | Add16 | Add32 | Add64 | Sub8
+            | Sub16 | Sub32 | Sub64 | Set8 | Set16 | Set32 => RelocationFormula::Absolute,
+            Branch | Jal | Call | CallPlt | PcrelHi20 | RvcBranch | RvcJump | Pcrel32 => {
+                RelocationFormula::PcRelative
+            }
+            GotHi20 => RelocationFormula::GotEntryPcRelative,
+            PcrelLo12I | PcrelLo12S => RelocationFormula::PairedPcRelative,
+        }
+    }
+
+    pub fn field(&self) -> RelocationField {
+        use RiscVRelocationType::*;
+        let (width, shift, bits, overflow, alignment, layout) = match self {
+            Direct32 => return RelocationField::word(4, OverflowCheck::SignedOrUnsigned),
+            Direct64 => return RelocationField::word(8, OverflowCheck::None),
+            Pcrel32 => return RelocationField::word(4, OverflowCheck::Signed),
+            Set8 => return RelocationField::word(1, OverflowCheck::None),
+            Set16 => return RelocationField::word(2, OverflowCheck::None),
+            Set32 => return RelocationField::word(4, OverflowCheck::None),
+            Branch => (4, 0, 13, OverflowCheck::Signed, 2, FieldLayout::Bits(B_IMM)),
+            Jal => (4, 0, 21, OverflowCheck::Signed, 2, FieldLayout::Bits(J_IMM)),
+            Call | CallPlt => (
+                8,
+                12,
+                20,
+                OverflowCheck::Signed,
+                1,
+                FieldLayout::Split {
+                    high: U_IMM,
+                    low: CALL_LOW_IMM,
+                },
+            ),
+            GotHi20 | PcrelHi20 | Hi20 => (
+                4,
+                12,
+                20,
+                OverflowCheck::Signed,
+                1,
+                FieldLayout::Split {
+                    high: U_IMM,
+                    low: &[],
+                },
+            ),
+            // The high part rounds, so the remainder is a signed 12-bit value
+            PcrelLo12I | Lo12I => (4, 0, 12, OverflowCheck::None, 1, FieldLayout::Bits(I_IMM)),
+            PcrelLo12S | Lo12S => (4, 0, 12, OverflowCheck::None, 1, FieldLayout::Bits(S_IMM)),
+            Add8 => (1, 0, 8, OverflowCheck::None, 1, FieldLayout::Add),
+            Add16 => (2, 0, 16, OverflowCheck::None, 1, FieldLayout::Add),
+            Add32 => (4, 0, 32, OverflowCheck::None, 1, FieldLayout::Add),
+            Add64 => (8, 0, 64, OverflowCheck::None, 1, FieldLayout::Add),
+            Sub8 => (1, 0, 8, OverflowCheck::None, 1, FieldLayout::Subtract),
+            Sub16 => (2, 0, 16, OverflowCheck::None, 1, FieldLayout::Subtract),
+            Sub32 => (4, 0, 32, OverflowCheck::None, 1, FieldLayout::Subtract),
+            Sub64 => (8, 0, 64, OverflowCheck::None, 1, FieldLayout::Subtract),
+            RvcBranch => (2, 0, 9, OverflowCheck::Signed, 2, FieldLayout::Bits(CB_IMM)),
+            RvcJump => (
+                2,
+                0,
+                12,
+                OverflowCheck::Signed,
+                2,
+                FieldLayout::Bits(CJ_IMM),
+            ),
+        };
+        RelocationField {
+            width,
+            shift,
+            bits,
+            overflow,
+            alignment,
+            layout,
+        }
+    }
+
+    pub fn is_branch(&self) -> bool {
+        use RiscVRelocationType::*;
+        matches!(self, Branch | Jal | Call | CallPlt | RvcBranch | RvcJump)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        core::models::Architecture,
+        elf::read_relocatable_object,
+        in_memory::{link, models::LinkConfig, LinkError, LinkInput},
+    };
+
+    use super::*;
+    use RiscVRelocationType::*;
+
+    const RISCV_O: &[u8] = include_bytes!("../../../fixtures/riscv.o");
+
+    /// Stores `value` in the field of `typ` in `instruction`, which holds the
+    /// `auipc`/`jalr` pair of a call in its low and high words.
+    fn encode(typ: RiscVRelocationType, instruction: u64, value: i64) -> u64 {
+        let field = typ.field();
+        assert!(field.fits(value as i128), "{typ:?} overflows");
+        field.insert(instruction as usize, value as usize) as u64
+    }
+
+    #[test]
+    fn encode_fields() {
+        // Compared with `llvm-mc -triple=riscv64 -mattr=+c`; the field of each
+        // instruction is zeroed first
+        // lui a2, 0x12346, rounded up for the `addi` below
+        assert_eq!(encode(Hi20, 0x00000637, 0x12345800), 0x12346637);
+        // addi a2, a2, -0x800
+        assert_eq!(encode(Lo12I, 0x00060613, 0x12345800), 0x80060613);
+        // sd a1, -8(a0)
+        assert_eq!(encode(Lo12S, 0x00b53023, -8), 0xfeb53c23);
+        // beq a0, a1, 0x800 and beq a0, a1, -0x1000
+        assert_eq!(encode(Branch, 0x00b50063, 0x800), 0x00b500e3);
+        assert_eq!(encode(Branch, 0x00b50063, -0x1000), 0x80b50063);
+        // jal 0x12344 and jal -4
+        assert_eq!(encode(Jal, 0x000000ef, 0x12344), 0x344120ef);
+        assert_eq!(encode(Jal, 0x000000ef, -4), 0xffdff0ef);
+        // c.beqz a0, -0x100 and c.bnez a0, 0xfe
+        assert_eq!(encode(RvcBranch, 0xc101, -0x100), 0xd101);
+        assert_eq!(encode(RvcBranch, 0xe101, 0xfe), 0xed7d);
+        // c.j 0x7fe and c.j -0x800
+        assert_eq!(encode(RvcJump, 0xa001, 0x7fe), 0xaffd);
+        assert_eq!(encode(RvcJump, 0xa001, -0x800), 0xb001);
+        // auipc ra, 0x12346; jalr ra, -0x800(ra)
+        assert_eq!(
+            encode(Call, 0x000080e7_00000097, 0x12345800),
+            0x800080e7_12346097
+        );
+
+        // Symbol differences accumulate in data words
+        assert_eq!(encode(Add32, 5, 3), 8);
+        assert_eq!(encode(Sub32, 5, 3), 2);
+        assert_eq!(encode(Set8, 5, 3), 3);
+    }
+
+    #[test]
+    fn overflow() {
+        let fits = |typ: RiscVRelocationType, value| typ.field().fits(value);
+        assert!(fits(Branch, -0x1000) && !fits(Branch, 0x1000));
+        assert!(fits(Jal, -0x10_0000) && !fits(Jal, 0x10_0000));
+        assert!(fits(RvcBranch, -0x100) && !fits(RvcBranch, 0x100));
+        assert!(fits(RvcJump, -0x800) && !fits(RvcJump, 0x800));
+        // The high part is rounded up when the low part is negative
+        assert!(fits(Call, 0x7fff_f7ff) && !fits(Call, 0x7fff_f800));
+        assert!(fits(Hi20, -0x8000_0800) && !fits(Hi20, -0x8000_0801));
+    }
+
+    #[test]
+    fn link_riscv() {
+        let objects = vec![LinkInput::Object(
+            read_relocatable_object("riscv.o", RISCV_O).unwrap(),
+        )];
+        let config = LinkConfig {
+            architecture: Architecture::RiscV,
+            ..Default::default()
+        };
+        let executable = link(objects, &config).unwrap();
+        let section_table = executable.section_table;
+
+        // `target` follows `_start` at 0x40002c, `value` is at 0x40102e
+        let text = section_table
+            .section_by_name(".text")
+            .unwrap()
+            .data
+            .bytes()
+            .unwrap();
+        assert_eq!(
+            text,
+            &[
+                0x97, 0x00, 0x00, 0x00, // auipc ra, 0
+                0xe7, 0x80, 0xc0, 0x02, // jalr 44(ra)
+                0x17, 0x15, 0x00, 0x00, // auipc a0, %pcrel_hi(value)
+                0x13, 0x05, 0x65, 0x02, // addi a0, a0, %pcrel_lo(.Lpcrel_hi0)
+                0x23, 0x33, 0xb5, 0x02, // sd a1, %pcrel_lo(.Lpcrel_hi0)(a0)
+                0x37, 0x16, 0x40, 0x00, // lui a2, %hi(value)
+                0x13, 0x06, 0xe6, 0x02, // addi a2, a2, %lo(value)
+                0x63, 0x08, 0xb5, 0x00, // beq a0, a1, target
+                0xef, 0x00, 0xc0, 0x00, // jal target
+                0x63, 0x04, 0x05, 0x00, // beqz a0, target
+                0x6f, 0x00, 0x40, 0x00, // j target
+                0x82, 0x80, // target: ret
+            ]
+        );
+        let data = section_table
+            .section_by_name(".data")
+            .unwrap()
+            .data
+            .bytes()
+            .unwrap();
+        assert_eq!(&data[..8], &0x40002c_u64.to_le_bytes());
+        // `target - _start`, from an `ADD32`/`SUB32` pair
+        assert_eq!(&data[8..], &0x2c_u32.to_le_bytes());
+    }
+
+    #[test]
+    fn link_riscv_pie() {
+        let objects = vec![LinkInput::Object(
+            read_relocatable_object("riscv.o", RISCV_O).unwrap(),
+        )];
+        let config = LinkConfig {
+            pie: true,
+            architecture: Architecture::RiscV,
+            ..Default::default()
+        };
+        let Err(diagnostics) = link(objects, &config) else {
+            panic!("`lui`/`addi` of `value` are position-dependent");
+        };
+
+        // `target - _start` in `.data` holds wherever the image is loaded
+        let origins = diagnostics
+            .iter()
+            .map(|diagnostic| match &diagnostic.error {
+                LinkError::PositionDependentRelocation { origin, .. } => origin.to_string(),
+                error => panic!("unexpected error: {error}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(origins, ["riscv.o:(.text+0x14)", "riscv.o:(.text+0x18)"]);
+    }
+}
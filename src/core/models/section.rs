@@ -1,5 +1,5 @@
 // #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub trait SectionIndex: Copy + Clone + PartialEq + Eq + std::fmt::Debug {}
+pub trait SectionIndex: Copy + Clone + PartialEq + Eq + std::hash::Hash + std::fmt::Debug {}
 
 pub trait LoadableSectionTable<S>
 where
@@ -8,3 +8,24 @@ where
     fn len(&self, index: S) -> usize;
     fn address(&self, index: S) -> usize;
 }
+
+/// Where each input section of an object ended up after merging.
+pub trait SectionMapping<S>
+where
+    S: SectionIndex,
+{
+    fn placement(&self, input: S) -> Option<SectionPlacement<S>>;
+    /// The name of the input section, for diagnostics.
+    fn name(&self, input: S) -> Option<&str>;
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SectionPlacement<S>
+where
+    S: SectionIndex,
+{
+    /// The output section.
+    pub section: S,
+    /// The offset of the input section within the output section.
+    pub offset: usize,
+}
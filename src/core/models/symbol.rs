@@ -1,4 +1,4 @@
-use std::{collections::HashMap, ops::Deref};
+use std::{collections::HashMap, ops::Deref, sync::Arc};
 
 use super::SectionIndex;
 
@@ -9,6 +9,16 @@ where
 {
     pub name: &'name str,
     pub value: SymbolValue<S>,
+    pub binding: SymbolBinding,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SymbolBinding {
+    /// Only visible to references from the object that defines it.
+    Local,
+    Global,
+    /// Yields to a global definition; resolves to address 0 if never defined.
+    Weak,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -32,13 +42,43 @@ where
     pub fn get(&self, index: SymbolIndex) -> &Symbol<'name, S> {
         &self.0[index.0]
     }
+    pub fn contains(&self, index: SymbolIndex) -> bool {
+        index.0 < self.0.len()
+    }
     pub fn replace(&mut self, index: SymbolIndex, symbol: Symbol<'name, S>) {
         self.0[index.0] = symbol;
     }
     pub fn len(&self) -> usize {
         self.0.len()
     }
-    pub fn into_iter(self) -> impl Iterator<Item = (SymbolIndex, Symbol<'name, S>)> {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (SymbolIndex, &Symbol<'name, S>)> {
+        self.0
+            .iter()
+            .enumerate()
+            .map(|(index, symbol)| (SymbolIndex(index), symbol))
+    }
+}
+impl<'name, S> Default for SymbolTable<'name, S>
+where
+    S: SectionIndex,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<'name, S> IntoIterator for SymbolTable<'name, S>
+where
+    S: SectionIndex,
+{
+    type Item = (SymbolIndex, Symbol<'name, S>);
+    type IntoIter = std::iter::Map<
+        std::iter::Enumerate<std::vec::IntoIter<Symbol<'name, S>>>,
+        fn((usize, Symbol<'name, S>)) -> (SymbolIndex, Symbol<'name, S>),
+    >;
+    fn into_iter(self) -> Self::IntoIter {
         self.0
             .into_iter()
             .enumerate()
@@ -53,6 +93,15 @@ where
 {
     Undefined,
     Defined(SymbolDefinition<S>),
+    /// A tentative definition (e.g. an uninitialized C global built with `-fcommon`)
+    /// that is allocated by the linker unless a real definition is found.
+    Common {
+        size: usize,
+        alignment: usize,
+    },
+    /// A value that is not an address in any section (e.g. from `.set`), which
+    /// relocations use as is.
+    Absolute(usize),
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -71,6 +120,8 @@ where
 {
     inner: SymbolTable<'name, S>,
     indices: HashMap<&'name str, SymbolIndex>,
+    /// The object that provided the current value of each symbol.
+    objects: Vec<Arc<str>>,
 }
 impl<'name, S> Deref for ResolvingSymbolTable<'name, S>
 where
@@ -89,25 +140,50 @@ where
         Self {
             inner: SymbolTable::new(),
             indices: HashMap::new(),
+            objects: Vec::new(),
         }
     }
 
-    pub fn add(&mut self, symbol: Symbol<'name, S>) -> SymbolIndex {
-        let index = self.inner.add(symbol);
+    pub fn add(&mut self, symbol: Symbol<'name, S>, object: &Arc<str>) -> SymbolIndex {
+        let index = self.add_local(symbol, object);
         self.indices.insert(self.inner.get(index).name, index);
         index
     }
 
+    /// Adds a symbol that cannot be looked up by name.
+    pub fn add_local(&mut self, symbol: Symbol<'name, S>, object: &Arc<str>) -> SymbolIndex {
+        self.objects.push(object.clone());
+        self.inner.add(symbol)
+    }
+
     pub fn get(&self, index: SymbolIndex) -> &Symbol<'name, S> {
         self.inner.get(index)
     }
 
-    pub fn get_by_name(&self, name: &'name str) -> Option<&Symbol<'name, S>> {
-        self.indices.get(&name).map(|index| self.inner.get(*index))
+    pub fn get_by_name(&self, name: &str) -> Option<&Symbol<'name, S>> {
+        self.indices.get(name).map(|index| self.inner.get(*index))
+    }
+
+    pub fn get_index_by_name(&self, name: &str) -> Option<SymbolIndex> {
+        self.indices.get(name).copied()
+    }
+
+    /// Whether the symbol is resolved by name across objects, so that a shared object
+    /// exports it if defined and imports it otherwise.
+    pub fn is_global(&self, index: SymbolIndex) -> bool {
+        let symbol = self.inner.get(index);
+        symbol.binding != SymbolBinding::Local && self.indices.get(symbol.name) == Some(&index)
+    }
+
+    /// The object that defines the symbol, or first referenced it if undefined.
+    pub fn object(&self, index: SymbolIndex) -> &Arc<str> {
+        &self.objects[index.0]
     }
 
-    pub fn get_index_by_name(&self, name: &'name str) -> Option<SymbolIndex> {
-        self.indices.get(&name).copied()
+    /// Replaces the symbol with a definition from another object.
+    pub fn redefine(&mut self, index: SymbolIndex, symbol: Symbol<'name, S>, object: &Arc<str>) {
+        self.replace(index, symbol);
+        self.objects[index.0] = object.clone();
     }
 
     pub fn replace(&mut self, index: SymbolIndex, symbol: Symbol<'name, S>) {
@@ -117,3 +193,11 @@ where
         self.inner.replace(index, symbol);
     }
 }
+impl<'name, S> Default for ResolvingSymbolTable<'name, S>
+where
+    S: SectionIndex,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
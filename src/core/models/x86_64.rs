@@ -0,0 +1,165 @@
+use super::{OverflowCheck, RelocationField, RelocationFormula};
+
+/// The relocation types of the x86-64 psABI that can appear in relocatable objects.
+///
+/// Calls through the PLT resolve directly to the symbol in a static link.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum X86_64RelocationType {
+    /// `R_X86_64_64`
+    Direct64,
+    /// `R_X86_64_PC32`
+    Pc32,
+    /// `R_X86_64_GOT32`
+    Got32,
+    /// `R_X86_64_PLT32`
+    Plt32,
+    /// `R_X86_64_GOTPCREL`
+    GotPcRel,
+    /// `R_X86_64_32`
+    Direct32,
+    /// `R_X86_64_32S`
+    Direct32Signed,
+    /// `R_X86_64_16`
+    Direct16,
+    /// `R_X86_64_PC16`
+    Pc16,
+    /// `R_X86_64_8`
+    Direct8,
+    /// `R_X86_64_PC8`
+    Pc8,
+    /// `R_X86_64_PC64`
+    Pc64,
+    /// `R_X86_64_GOTOFF64`
+    GotOff64,
+    /// `R_X86_64_GOTPC32`
+    GotPc32,
+    /// `R_X86_64_GOT64`
+    Got64,
+    /// `R_X86_64_GOTPCREL64`
+    GotPcRel64,
+    /// `R_X86_64_GOTPC64`
+    GotPc64,
+    /// `R_X86_64_GOTPLT64`
+    GotPlt64,
+    /// `R_X86_64_PLTOFF64`
+    PltOff64,
+    /// `R_X86_64_SIZE32`
+    Size32,
+    /// `R_X86_64_SIZE64`
+    Size64,
+    /// `R_X86_64_GOTPCRELX`
+    GotPcRelX,
+    /// `R_X86_64_REX_GOTPCRELX`
+    RexGotPcRelX,
+}
+impl X86_64RelocationType {
+    pub fn formula(&self) -> RelocationFormula {
+        use X86_64RelocationType::*;
+        match self {
+            Direct64 | Direct32 | Direct32Signed | Direct16 | Direct8 => {
+                RelocationFormula::Absolute
+            }
+            Pc32 | Plt32 | Pc16 | Pc8 | Pc64 => RelocationFormula::PcRelative,
+            Got32 | Got64 | GotPlt64 => RelocationFormula::GotEntry,
+            GotPcRel | GotPcRel64 | GotPcRelX | RexGotPcRelX => {
+                RelocationFormula::GotEntryPcRelative
+            }
+            GotOff64 | PltOff64 => RelocationFormula::GotRelative,
+            GotPc32 | GotPc64 => RelocationFormula::GotPcRelative,
+            Size32 | Size64 => RelocationFormula::Size,
+        }
+    }
+
+    pub fn field(&self) -> RelocationField {
+        use X86_64RelocationType::*;
+        let (width, overflow) = match self {
+            Direct64 | Pc64 | GotOff64 | Got64 | GotPcRel64 | GotPc64 | GotPlt64 | PltOff64
+            | Size64 => (8, OverflowCheck::None),
+            Pc32 | Plt32 | GotPcRel | Direct32Signed | GotPc32 | GotPcRelX | RexGotPcRelX => {
+                (4, OverflowCheck::Signed)
+            }
+            Direct32 | Got32 | Size32 => (4, OverflowCheck::Unsigned),
+            Direct16 => (2, OverflowCheck::SignedOrUnsigned),
+            Pc16 => (2, OverflowCheck::Signed),
+            Direct8 => (1, OverflowCheck::SignedOrUnsigned),
+            Pc8 => (1, OverflowCheck::Signed),
+        };
+        RelocationField::word(width, overflow)
+    }
+
+    /// `R_X86_64_PC32` is also used to take addresses, so only calls through the
+    /// PLT count.
+    pub fn is_branch(&self) -> bool {
+        matches!(self, X86_64RelocationType::Plt32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use X86_64RelocationType::*;
+
+    /// Stores `value` in the field of `typ` at `offset` in `instruction`.
+    fn encode(typ: X86_64RelocationType, instruction: &[u8], offset: usize, value: i64) -> Vec<u8> {
+        let field = typ.field();
+        assert!(field.fits(value as i128), "{typ:?} overflows");
+        let mut bytes = instruction.to_vec();
+        let data = &mut bytes[offset..offset + field.width];
+        let mut current = [0; 8];
+        current[..field.width].copy_from_slice(data);
+        let value = field.insert(usize::from_le_bytes(current), value as usize);
+        data.copy_from_slice(&value.to_le_bytes()[..field.width]);
+        bytes
+    }
+
+    #[test]
+    fn encode_fields() {
+        // Compared with `as --64`; the field of each instruction is zeroed first
+        // movabs rax, 0x1122334455667788
+        let movabs = [0x48, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(
+            encode(Direct64, &movabs, 2, 0x1122334455667788),
+            [0x48, 0xb8, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11]
+        );
+        // mov eax, 0x11223344
+        assert_eq!(
+            encode(Direct32, &[0xb8, 0, 0, 0, 0], 1, 0x11223344),
+            [0xb8, 0x44, 0x33, 0x22, 0x11]
+        );
+        // mov rax, -0x11223344
+        assert_eq!(
+            encode(
+                Direct32Signed,
+                &[0x48, 0xc7, 0xc0, 0, 0, 0, 0],
+                3,
+                -0x11223344
+            ),
+            [0x48, 0xc7, 0xc0, 0xbc, 0xcc, 0xdd, 0xee]
+        );
+        // mov ax, 0x1122
+        assert_eq!(
+            encode(Direct16, &[0x66, 0xb8, 0, 0], 2, 0x1122),
+            [0x66, 0xb8, 0x22, 0x11]
+        );
+        // mov al, 0x11
+        assert_eq!(encode(Direct8, &[0xb0, 0], 1, 0x11), [0xb0, 0x11]);
+        // call .+0x1000
+        assert_eq!(
+            encode(Pc32, &[0xe8, 0, 0, 0, 0], 1, 0xffb),
+            [0xe8, 0xfb, 0x0f, 0x00, 0x00]
+        );
+        // jmp .
+        assert_eq!(encode(Pc8, &[0xeb, 0], 1, -2), [0xeb, 0xfe]);
+    }
+
+    #[test]
+    fn overflow() {
+        let fits = |typ: X86_64RelocationType, value| typ.field().fits(value);
+        assert!(fits(Direct64, -1) && fits(Direct64, u64::MAX as i128));
+        assert!(fits(Direct32, 0xffff_ffff) && !fits(Direct32, -1));
+        assert!(fits(Direct32Signed, -0x8000_0000) && !fits(Direct32Signed, 0x8000_0000));
+        assert!(fits(Direct16, -0x8000) && fits(Direct16, 0xffff));
+        assert!(!fits(Direct16, -0x8001) && !fits(Direct16, 0x1_0000));
+        assert!(fits(Pc8, -0x80) && !fits(Pc8, 0x80));
+    }
+}
@@ -1,4 +1,4 @@
-use std::num::Wrapping;
+use std::collections::{HashMap, HashSet};
 
 use thiserror::Error;
 
@@ -6,6 +6,8 @@ use super::models::*;
 
 pub fn relocate_reference<S>(
     reference: &ResolvingRelocation<S>,
+    high_parts: &HighPartRelocations<S>,
+    got: Option<&GlobalOffsetTable<S>>,
     symbol_table: &ResolvingSymbolTable<S>,
     section_table: &impl LoadableSectionTable<S>,
 ) -> Result<usize, RelocationError>
@@ -13,56 +15,338 @@ where
     S: SectionIndex,
 {
     // Extract helpful information
-    let reference_section = reference.section;
     let symbol = symbol_table.get(reference.symbol);
-    let SymbolValue::Defined(symbol_definition) =
-        symbol.value else {
-            return Err(RelocationError::SymbolNotDefined {
-                symbol: symbol.name.to_string()
-            });
-        };
-    let symbol_section = symbol_definition.section;
-    let symbol_offset = symbol_definition.offset;
 
     // Calculate new reference value
-    let new_reference_value = relocate_reference_(
-        &reference,
-        symbol_offset,
-        section_table.address(symbol_section),
-        section_table.address(reference_section),
-    );
-
-    Ok(new_reference_value)
+    let new_reference_value = evaluate(reference, high_parts, got, symbol_table, section_table)?;
+    let field = reference.typ.field();
+    if new_reference_value % field.alignment as i128 != 0 {
+        return Err(RelocationError::Misaligned {
+            symbol: symbol.name.to_string(),
+            origin: reference.origin().clone(),
+            value: new_reference_value,
+            alignment: field.alignment,
+        });
+    }
+    if !field.fits(new_reference_value) {
+        return Err(RelocationError::Overflow {
+            symbol: symbol.name.to_string(),
+            origin: reference.origin().clone(),
+            value: new_reference_value,
+        });
+    }
+
+    // Keep the two's complement bits that are written to the field
+    Ok(new_reference_value as usize)
+}
+
+/// The relocations that compute the high part of a value split across two
+/// instructions, by the place they patch.
+pub struct HighPartRelocations<'reference, S>(
+    HashMap<(S, usize), &'reference ResolvingRelocation<S>>,
+)
+where
+    S: SectionIndex;
+impl<'reference, S> HighPartRelocations<'reference, S>
+where
+    S: SectionIndex,
+{
+    pub fn new(references: impl IntoIterator<Item = &'reference ResolvingRelocation<S>>) -> Self {
+        let high_parts = references
+            .into_iter()
+            .filter(|reference| {
+                matches!(reference.typ.field().layout, FieldLayout::Split { .. })
+                    && reference.typ.formula() != RelocationFormula::PairedPcRelative
+            })
+            .map(|reference| ((reference.section, reference.offset), reference))
+            .collect();
+        Self(high_parts)
+    }
+    pub fn get(&self, section: S, offset: usize) -> Option<&'reference ResolvingRelocation<S>> {
+        self.0.get(&(section, offset)).copied()
+    }
+}
+
+/// The global offset table: one address-sized entry for each symbol that
+/// relocations reach through it, filled in by the linker unless the symbol is
+/// preemptible, in which case the loader binds it.
+pub struct GlobalOffsetTable<S>
+where
+    S: SectionIndex,
+{
+    placement: SectionPlacement<S>,
+    /// The offset of each symbol's entry within the table.
+    entries: HashMap<SymbolIndex, usize>,
+    preemptible: HashSet<SymbolIndex>,
+}
+impl<S> GlobalOffsetTable<S>
+where
+    S: SectionIndex,
+{
+    /// Every supported target has 64-bit addresses.
+    pub const ENTRY_SIZE: usize = 8;
+
+    /// Whether any of the relocations refers to the table.
+    pub fn is_used<'reference>(
+        references: impl IntoIterator<Item = &'reference ResolvingRelocation<S>>,
+    ) -> bool
+    where
+        S: 'reference,
+    {
+        references
+            .into_iter()
+            .any(|reference| uses_got(reference.typ.formula()))
+    }
+    /// The symbols that need an entry, in order of first reference.
+    pub fn symbols<'reference>(
+        references: impl IntoIterator<Item = &'reference ResolvingRelocation<S>>,
+    ) -> Vec<SymbolIndex>
+    where
+        S: 'reference,
+    {
+        let mut seen = HashSet::new();
+        references
+            .into_iter()
+            .filter(|reference| {
+                matches!(
+                    reference.typ.formula(),
+                    RelocationFormula::GotEntry | RelocationFormula::GotEntryPcRelative
+                )
+            })
+            .map(|reference| reference.symbol)
+            .filter(|symbol| seen.insert(*symbol))
+            .collect()
+    }
+    /// Lays out the entries of `symbols` in order, from `placement`.
+    pub fn new(
+        placement: SectionPlacement<S>,
+        symbols: &[SymbolIndex],
+        is_preemptible: impl Fn(SymbolIndex) -> bool,
+    ) -> Self {
+        let entries = symbols
+            .iter()
+            .enumerate()
+            .map(|(i, symbol)| (*symbol, i * Self::ENTRY_SIZE))
+            .collect();
+        let preemptible = symbols
+            .iter()
+            .copied()
+            .filter(|symbol| is_preemptible(*symbol))
+            .collect();
+        Self {
+            placement,
+            entries,
+            preemptible,
+        }
+    }
+    pub fn placement(&self) -> SectionPlacement<S> {
+        self.placement
+    }
+    pub fn address(&self, section_table: &impl LoadableSectionTable<S>) -> usize {
+        section_table.address(self.placement.section) + self.placement.offset
+    }
+    /// The offset of the symbol's entry within the table.
+    pub fn entry(&self, symbol: SymbolIndex) -> Option<usize> {
+        self.entries.get(&symbol).copied()
+    }
+    /// Whether the loader fills in the symbol's entry.
+    pub fn is_preemptible(&self, symbol: SymbolIndex) -> bool {
+        self.preemptible.contains(&symbol)
+    }
+    /// The offset of each entry within its output section, and what it holds.
+    ///
+    /// Entries of other undefined symbols are left out, as relocations through them
+    /// fail.
+    pub fn values<'a>(
+        &'a self,
+        symbol_table: &'a ResolvingSymbolTable<S>,
+        section_table: &'a impl LoadableSectionTable<S>,
+    ) -> impl Iterator<Item = (usize, GotValue)> + 'a {
+        self.entries.iter().filter_map(|(&index, &entry)| {
+            let symbol = symbol_table.get(index);
+            let value = match (self.is_preemptible(index), symbol.value, symbol.binding) {
+                (true, ..) => GotValue::Preemptible(index),
+                (false, SymbolValue::Defined(_), _) => {
+                    GotValue::Address(symbol_address(symbol, section_table)?.0)
+                }
+                (false, SymbolValue::Absolute(value), _) => GotValue::Absolute(value),
+                (false, SymbolValue::Undefined, SymbolBinding::Weak) => GotValue::Null,
+                (false, ..) => return None,
+            };
+            Some((self.placement.offset + entry, value))
+        })
+    }
+}
+
+/// What an entry of the global offset table holds.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GotValue {
+    /// The link-time address of a defined symbol.
+    Address(usize),
+    /// 0 wherever the image is loaded, for an undefined weak symbol.
+    Null,
+    /// The value of an absolute symbol, wherever the image is loaded.
+    Absolute(usize),
+    /// The address that the loader binds the symbol to.
+    Preemptible(SymbolIndex),
+}
+
+fn uses_got(formula: RelocationFormula) -> bool {
+    matches!(
+        formula,
+        RelocationFormula::GotEntry
+            | RelocationFormula::GotEntryPcRelative
+            | RelocationFormula::GotRelative
+            | RelocationFormula::GotPcRelative
+    )
 }
 
 #[derive(Debug, Error, PartialEq, Eq, Clone)]
 pub enum RelocationError {
-    #[error("Symbol `{symbol}` not defined")]
-    SymbolNotDefined { symbol: String },
+    #[error("Undefined reference to `{symbol}` in {origin}")]
+    SymbolNotDefined {
+        symbol: String,
+        origin: RelocationOrigin,
+    },
+    #[error("Relocation against `{symbol}` in {origin} needs a global offset table")]
+    NoGlobalOffsetTable {
+        symbol: String,
+        origin: RelocationOrigin,
+    },
+    #[error("No high part relocation at `{symbol}` for {origin}")]
+    MissingHighPart {
+        symbol: String,
+        origin: RelocationOrigin,
+    },
+    #[error("Relocation against `{symbol}` in {origin} overflows with value {value:#x}")]
+    Overflow {
+        symbol: String,
+        origin: RelocationOrigin,
+        value: i128,
+    },
+    #[error(
+        "Relocation against `{symbol}` in {origin} has value {value:#x}, which is not a multiple of {alignment}"
+    )]
+    Misaligned {
+        symbol: String,
+        origin: RelocationOrigin,
+        value: i128,
+        alignment: usize,
+    },
 }
 
-fn relocate_reference_<S>(
+fn evaluate<S>(
     reference: &ResolvingRelocation<S>,
-    symbol_offset: usize,
-    new_symbol_section_address: usize,
-    new_ref_section_address: usize,
-) -> usize
+    high_parts: &HighPartRelocations<S>,
+    got: Option<&GlobalOffsetTable<S>>,
+    symbol_table: &ResolvingSymbolTable<S>,
+    section_table: &impl LoadableSectionTable<S>,
+) -> Result<i128, RelocationError>
 where
     S: SectionIndex,
 {
-    let new_reference_value = match reference.typ {
-        RelocationType::PcRelative => {
-            let new_ref_address = reference.offset + new_ref_section_address;
-            let new_symbol_address = symbol_offset + new_symbol_section_address;
-            let relative_new_symbol_address =
-                Wrapping(new_symbol_address) - Wrapping(new_ref_address);
-            relative_new_symbol_address.0
-        }
-        RelocationType::Absolute => {
-            let new_symbol_address = symbol_offset + new_symbol_section_address;
-            new_symbol_address
+    let symbol = symbol_table.get(reference.symbol);
+
+    // The symbol labels the instruction of the high part, whose value is reused
+    if reference.typ.formula() == RelocationFormula::PairedPcRelative {
+        let high_part = match symbol.value {
+            SymbolValue::Defined(definition) => {
+                high_parts.get(definition.section, definition.offset)
+            }
+            _ => None,
+        };
+        let Some(high_part) = high_part else {
+            return Err(RelocationError::MissingHighPart {
+                symbol: symbol.name.to_string(),
+                origin: reference.origin().clone(),
+            });
+        };
+        return evaluate(high_part, high_parts, got, symbol_table, section_table);
+    }
+
+    // Only the entry of a symbol bound by the loader is needed
+    let bound_at_load = matches!(
+        reference.typ.formula(),
+        RelocationFormula::GotEntry | RelocationFormula::GotEntryPcRelative
+    ) && got.is_some_and(|got| got.is_preemptible(reference.symbol));
+    let (new_symbol_address, symbol_size) = match symbol_address(symbol, section_table) {
+        Some(address) => address,
+        None if bound_at_load => (0, 0),
+        None => {
+            return Err(RelocationError::SymbolNotDefined {
+                symbol: symbol.name.to_string(),
+                origin: reference.origin().clone(),
+            });
         }
     };
+    let got_address = got.map(|got| got.address(section_table));
+    let got_entry = got.and_then(|got| got.entry(reference.symbol));
+    relocate_reference_(
+        reference,
+        new_symbol_address,
+        symbol_size,
+        section_table.address(reference.section),
+        got_address,
+        got_entry,
+    )
+    .ok_or_else(|| RelocationError::NoGlobalOffsetTable {
+        symbol: symbol.name.to_string(),
+        origin: reference.origin().clone(),
+    })
+}
+
+/// The address and size of a symbol, or `None` if it is not defined.
+fn symbol_address<S>(
+    symbol: &Symbol<S>,
+    section_table: &impl LoadableSectionTable<S>,
+) -> Option<(usize, usize)>
+where
+    S: SectionIndex,
+{
+    match (symbol.value, symbol.binding) {
+        (SymbolValue::Defined(symbol_definition), _) => Some((
+            section_table.address(symbol_definition.section) + symbol_definition.offset,
+            symbol_definition.size,
+        )),
+        (SymbolValue::Absolute(value), _) => Some((value, 0)),
+        // Undefined weak symbols resolve to address 0
+        (SymbolValue::Undefined, SymbolBinding::Weak) => Some((0, 0)),
+        (SymbolValue::Undefined | SymbolValue::Common { .. }, _) => None,
+    }
+}
+
+/// Evaluates the formula of the relocation type, if the GOT address and the
+/// symbol's GOT entry are known when it needs them.
+fn relocate_reference_<S>(
+    reference: &ResolvingRelocation<S>,
+    new_symbol_address: usize,
+    symbol_size: usize,
+    new_ref_section_address: usize,
+    got_address: Option<usize>,
+    got_entry: Option<usize>,
+) -> Option<i128>
+where
+    S: SectionIndex,
+{
+    let symbol = new_symbol_address as i128;
+    let addend = reference.addend as i128;
+    let place = (new_ref_section_address + reference.offset) as i128;
+    let got = || got_address.map(|address| address as i128);
+    let entry = || got_entry.map(|entry| entry as i128);
+    match reference.typ.formula() {
+        RelocationFormula::Absolute => Some(symbol + addend),
+        RelocationFormula::PcRelative => Some(symbol + addend - place),
+        RelocationFormula::PagePcRelative => Some(page(symbol + addend) - page(place)),
+        RelocationFormula::Size => Some(symbol_size as i128 + addend),
+        // Evaluated through the high part
+        RelocationFormula::PairedPcRelative => None,
+        RelocationFormula::GotEntry => Some(entry()? + addend),
+        RelocationFormula::GotEntryPcRelative => Some(entry()? + got()? + addend - place),
+        RelocationFormula::GotRelative => Some(symbol + addend - got()?),
+        RelocationFormula::GotPcRelative => Some(got()? + addend - place),
+    }
+}
 
-    (new_reference_value as isize + reference.addend) as usize
+fn page(address: i128) -> i128 {
+    address & !0xfff
 }
@@ -1,45 +1,91 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use thiserror::Error;
 
 use super::models::*;
 
-pub fn resolve_unloadable_sections<'name, S, ST>(
-    section_table: &ST,
+/// Resolves the symbols and relocations of an object against those linked so far.
+///
+/// Returns the errors of the symbols and relocations that could not be
+/// resolved; everything else is resolved regardless.
+pub fn resolve_unloadable_sections<'name, S, SM>(
+    object: &str,
+    section_mapping: &SM,
     symbol_table: &mut ResolvingSymbolTable<'name, S>,
     other_symbol_table: SymbolTable<'name, S>,
     relocation_table: &mut Vec<ResolvingRelocation<S>>,
     other_relocation_table: Vec<Relocation<S>>,
-) -> Result<(), ResolveError<S>>
+) -> Vec<ResolveError<S>>
 where
     S: SectionIndex,
-    ST: LoadableSectionTable<S>,
+    SM: SectionMapping<S>,
 {
+    let object: Arc<str> = object.into();
     let mut resolved_symbols = HashMap::with_capacity(other_symbol_table.len());
+    let mut enclosing_symbols = EnclosingSymbols::default();
+    let mut errors = vec![];
 
     // Resolve symbols
-    for (index, symbol) in other_symbol_table.into_iter() {
+    for (index, symbol) in other_symbol_table {
+        enclosing_symbols.add(&symbol);
+
         // Update offset
-        let new_index = resolve_symbol(section_table, symbol_table, &symbol)?;
+        let new_index = match symbol.binding {
+            // Local symbols never take part in resolution by name
+            SymbolBinding::Local => update_offset(&object, section_mapping, &symbol)
+                .map(|symbol| symbol_table.add_local(symbol, &object)),
+            SymbolBinding::Global | SymbolBinding::Weak => {
+                resolve_symbol(&object, section_mapping, symbol_table, &symbol, &mut errors)
+            }
+        };
+        let new_index = match new_index {
+            Ok(new_index) => new_index,
+            Err(error) => {
+                // Relocations against the symbol are reported as invalid
+                errors.push(error);
+                continue;
+            }
+        };
 
         // Mark the symbol as resolved
         resolved_symbols.insert(index, new_index);
     }
 
     // Resolve relocations
+    enclosing_symbols.sort();
+    let mut section_names: HashMap<S, Arc<str>> = HashMap::new();
     for reference in other_relocation_table.into_iter() {
-        let Some(new_symbol) = resolved_symbols.get(&reference.symbol) else {
-            return Err(ResolveError::InvalidRelocation {
+        let (Some(new_symbol), Some(placement), Some(section_name)) = (
+            resolved_symbols.get(&reference.symbol),
+            section_mapping.placement(reference.section),
+            section_mapping.name(reference.section),
+        ) else {
+            errors.push(ResolveError::InvalidRelocation {
+                object: object.to_string(),
                 relocation: reference,
-            })
+            });
+            continue;
+        };
+        let origin = RelocationOrigin {
+            object: object.clone(),
+            section: section_names
+                .entry(reference.section)
+                .or_insert_with(|| Arc::from(section_name))
+                .clone(),
+            offset: reference.offset,
+            symbol: enclosing_symbols.get(reference.section, reference.offset),
         };
 
-        // Update relocation offset and symbol index
-        let new_reference = ResolvingRelocation(Relocation {
-            symbol: *new_symbol,
-            offset: section_table.len(reference.section) + reference.offset,
-            ..reference
-        });
+        // Update relocation section, offset and symbol index
+        let new_reference = ResolvingRelocation(
+            Relocation {
+                symbol: *new_symbol,
+                section: placement.section,
+                offset: placement.offset + reference.offset,
+                ..reference
+            },
+            origin,
+        );
 
         // Store resolved relocation
         relocation_table.push(new_reference);
@@ -48,7 +94,7 @@ where
         resolved_symbols.insert(reference.symbol, *new_symbol);
     }
 
-    Ok(())
+    errors
 }
 
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
@@ -56,16 +102,93 @@ pub enum ResolveError<S>
 where
     S: SectionIndex,
 {
-    #[error("conflict `{symbol}` symbols")]
-    ConflictSymbols { symbol: String },
-    #[error("invalid relocation")]
-    InvalidRelocation { relocation: Relocation<S> },
+    #[error("multiple definitions of `{symbol}` in {first} and {second}")]
+    ConflictSymbols {
+        symbol: String,
+        first: String,
+        second: String,
+    },
+    #[error("invalid relocation in {object}")]
+    InvalidRelocation {
+        object: String,
+        relocation: Relocation<S>,
+    },
+    #[error("symbol `{symbol}` defined in unknown section in {object}")]
+    InvalidSymbol { object: String, symbol: String },
+}
+
+/// The sized symbol definitions of an input object, by section; sorted by start
+/// once all are added.
+struct EnclosingSymbols<S>(HashMap<S, Vec<EnclosingSymbol>>)
+where
+    S: SectionIndex;
+struct EnclosingSymbol {
+    start: usize,
+    end: usize,
+    /// The greatest end of this symbol and those sorted before it.
+    reach: usize,
+    name: Arc<str>,
+}
+impl<S> EnclosingSymbols<S>
+where
+    S: SectionIndex,
+{
+    fn add(&mut self, symbol: &Symbol<S>) {
+        let SymbolValue::Defined(definition) = symbol.value else {
+            return;
+        };
+        if definition.size == 0 {
+            return;
+        }
+        self.0
+            .entry(definition.section)
+            .or_default()
+            .push(EnclosingSymbol {
+                start: definition.offset,
+                end: definition.offset + definition.size,
+                reach: 0,
+                name: symbol.name.into(),
+            });
+    }
+    fn sort(&mut self) {
+        for symbols in self.0.values_mut() {
+            // Of symbols with the same start (aliases), the first added is found first
+            symbols.reverse();
+            symbols.sort_by_key(|symbol| symbol.start);
+            let mut reach = 0;
+            for symbol in symbols {
+                reach = reach.max(symbol.end);
+                symbol.reach = reach;
+            }
+        }
+    }
+    /// The innermost symbol that contains `offset`.
+    fn get(&self, section: S, offset: usize) -> Option<Arc<str>> {
+        let symbols = self.0.get(&section)?;
+        let starting_before = symbols.partition_point(|symbol| symbol.start <= offset);
+        symbols[..starting_before]
+            .iter()
+            .rev()
+            .take_while(|symbol| symbol.reach > offset)
+            .find(|symbol| offset < symbol.end)
+            .map(|symbol| symbol.name.clone())
+    }
+}
+impl<S> Default for EnclosingSymbols<S>
+where
+    S: SectionIndex,
+{
+    fn default() -> Self {
+        Self(HashMap::new())
+    }
 }
 
 fn resolve_symbol<'name, S>(
-    section_table: &impl LoadableSectionTable<S>,
+    object: &Arc<str>,
+    section_mapping: &impl SectionMapping<S>,
     symbol_table: &mut ResolvingSymbolTable<'name, S>,
     symbol: &Symbol<'name, S>,
+    errors: &mut Vec<ResolveError<S>>,
 ) -> Result<SymbolIndex, ResolveError<S>>
 where
     S: SectionIndex,
@@ -75,55 +198,107 @@ where
     let new_symbol_index = match existing_symbol_index {
         Some(existing_symbol_index) => {
             let existing_symbol = symbol_table.get(existing_symbol_index);
-            let _ = match (existing_symbol.value, symbol.value) {
+            let replace = match (existing_symbol.value, symbol.value) {
                 (SymbolValue::Undefined, SymbolValue::Undefined) => {
-                    return Ok(existing_symbol_index)
+                    // A single global reference makes the symbol required
+                    existing_symbol.binding == SymbolBinding::Weak
+                        && symbol.binding == SymbolBinding::Global
                 }
-                (SymbolValue::Undefined, SymbolValue::Defined(definition)) => definition,
-                (SymbolValue::Defined(_), SymbolValue::Undefined) => {
-                    return Ok(existing_symbol_index)
+                (SymbolValue::Undefined, _) => true,
+                (_, SymbolValue::Undefined) => false,
+                (
+                    SymbolValue::Defined(_) | SymbolValue::Absolute(_),
+                    SymbolValue::Defined(_) | SymbolValue::Absolute(_),
+                ) => {
+                    match (existing_symbol.binding, symbol.binding) {
+                        // The first definition is kept
+                        (SymbolBinding::Global, SymbolBinding::Global) => {
+                            errors.push(ResolveError::ConflictSymbols {
+                                symbol: symbol.name.to_string(),
+                                first: symbol_table.object(existing_symbol_index).to_string(),
+                                second: object.to_string(),
+                            });
+                            false
+                        }
+                        (SymbolBinding::Weak, SymbolBinding::Global) => true,
+                        // The first weak definition wins
+                        _ => false,
+                    }
                 }
-                (SymbolValue::Defined(_), SymbolValue::Defined(_)) => {
-                    return Err(ResolveError::ConflictSymbols {
-                        symbol: symbol.name.to_string(),
-                    })
+                (
+                    SymbolValue::Common {
+                        size: existing_size,
+                        alignment: existing_alignment,
+                    },
+                    SymbolValue::Common { size, alignment },
+                ) => {
+                    // Tentative definitions merge into the largest, most aligned one
+                    let new_symbol = Symbol {
+                        value: SymbolValue::Common {
+                            size: existing_size.max(size),
+                            alignment: existing_alignment.max(alignment),
+                        },
+                        ..*existing_symbol
+                    };
+                    symbol_table.replace(existing_symbol_index, new_symbol);
+                    return Ok(existing_symbol_index);
                 }
+                // A global definition overrides a tentative one, which overrides a weak one
+                (
+                    SymbolValue::Common { .. },
+                    SymbolValue::Defined(_) | SymbolValue::Absolute(_),
+                ) => symbol.binding == SymbolBinding::Global,
+                (
+                    SymbolValue::Defined(_) | SymbolValue::Absolute(_),
+                    SymbolValue::Common { .. },
+                ) => existing_symbol.binding == SymbolBinding::Weak,
             };
+            if !replace {
+                return Ok(existing_symbol_index);
+            }
 
             // Replace the existing symbol
-            let new_symbol = update_offset(section_table, symbol);
-            symbol_table.replace(existing_symbol_index, new_symbol);
+            let new_symbol = update_offset(object, section_mapping, symbol)?;
+            symbol_table.redefine(existing_symbol_index, new_symbol, object);
             existing_symbol_index
         }
         None => {
             // Add the symbol to symbol table
-            let new_symbol = update_offset(section_table, symbol);
-            let new_symbol_index = symbol_table.add(new_symbol);
-            new_symbol_index
+            let new_symbol = update_offset(object, section_mapping, symbol)?;
+            symbol_table.add(new_symbol, object)
         }
     };
 
-    return Ok(new_symbol_index);
+    Ok(new_symbol_index)
 }
 
 fn update_offset<'name, S>(
-    section_table: &impl LoadableSectionTable<S>,
+    object: &Arc<str>,
+    section_mapping: &impl SectionMapping<S>,
     symbol: &Symbol<'name, S>,
-) -> Symbol<'name, S>
+) -> Result<Symbol<'name, S>, ResolveError<S>>
 where
     S: SectionIndex,
 {
     match symbol.value {
-        SymbolValue::Undefined => *symbol,
+        SymbolValue::Undefined | SymbolValue::Common { .. } | SymbolValue::Absolute(_) => {
+            Ok(*symbol)
+        }
         SymbolValue::Defined(definition) => {
-            let new_offset = section_table.len(definition.section) + definition.offset;
-            Symbol {
+            let Some(placement) = section_mapping.placement(definition.section) else {
+                return Err(ResolveError::InvalidSymbol {
+                    object: object.to_string(),
+                    symbol: symbol.name.to_string(),
+                });
+            };
+            Ok(Symbol {
                 value: SymbolValue::Defined(SymbolDefinition {
-                    offset: new_offset,
+                    section: placement.section,
+                    offset: placement.offset + definition.offset,
                     ..definition
                 }),
                 ..*symbol
-            }
+            })
         }
     }
 }
@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::{
+    core::models::{SymbolBinding, SymbolValue},
+    in_memory::models::{InMemoryArchive, InMemoryArchiveMember, InMemoryRelocatableObject},
+};
+
+use super::{models::*, read_relocatable_object, ElfReadError};
+
+const MEMBER_HEADER_SIZE: usize = 60;
+
+/// Reads a System V/GNU static library (`.a`) of ELF64 relocatable objects.
+///
+/// The `/` (or `/SYM64/`) symbol index decides which member is linked for a
+/// symbol, and `//` holds long member names. Members are only parsed when linked,
+/// unless the archive has no symbol index to find them by.
+pub fn read_archive(bytes: &[u8]) -> Result<InMemoryArchive<'_>, ArchiveReadError> {
+    if !bytes.starts_with(&AR_MAGIC) {
+        return Err(ArchiveReadError::InvalidMagic);
+    }
+
+    let mut members: Vec<(&str, &[u8])> = Vec::new();
+    let mut member_offsets = HashMap::new();
+    let mut symbol_index = None;
+    let mut long_names: &[u8] = &[];
+    let mut offset = AR_MAGIC.len();
+    while offset < bytes.len() {
+        let header = archive_slice(bytes, offset, MEMBER_HEADER_SIZE, "member header")?;
+        if header[58..60] != AR_FMAG {
+            return Err(ArchiveReadError::InvalidHeader { offset });
+        }
+        let size = std::str::from_utf8(&header[48..58])
+            .ok()
+            .and_then(|size| size.trim_end().parse::<usize>().ok())
+            .ok_or(ArchiveReadError::InvalidHeader { offset })?;
+        let data = archive_slice(bytes, offset + MEMBER_HEADER_SIZE, size, "member")?;
+        match trim_end(&header[..16]) {
+            b"/" => symbol_index = Some((data, 4)),
+            b"/SYM64/" => symbol_index = Some((data, 8)),
+            b"//" => long_names = data,
+            name => {
+                let name = member_name(name, long_names)
+                    .ok_or(ArchiveReadError::InvalidName { offset })?;
+                member_offsets.insert(offset, members.len());
+                members.push((name, data));
+            }
+        }
+        // Members start at even offsets
+        offset += MEMBER_HEADER_SIZE + size.next_multiple_of(2);
+    }
+
+    let Some((data, width)) = symbol_index else {
+        let objects = members
+            .into_iter()
+            .map(|(name, data)| {
+                read_relocatable_object(name, data).map_err(|error| {
+                    ArchiveReadError::InvalidMember {
+                        member: name.to_string(),
+                        error,
+                    }
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        return Ok(InMemoryArchive {
+            symbol_index: index_members(&objects),
+            members: objects
+                .into_iter()
+                .map(InMemoryArchiveMember::parsed)
+                .collect(),
+        });
+    };
+    Ok(InMemoryArchive {
+        symbol_index: read_symbol_index(data, width, &member_offsets)?,
+        members: members
+            .into_iter()
+            .map(|(name, data)| InMemoryArchiveMember {
+                name,
+                parse: Box::new(move || {
+                    read_relocatable_object(name, data).map_err(|error| error.to_string())
+                }),
+            })
+            .collect(),
+    })
+}
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum ArchiveReadError {
+    #[error("Truncated {what}")]
+    Truncated { what: &'static str },
+    #[error("Not an archive")]
+    InvalidMagic,
+    #[error("Invalid member header at {offset:#x}")]
+    InvalidHeader { offset: usize },
+    #[error("Invalid member name at {offset:#x}")]
+    InvalidName { offset: usize },
+    #[error("Invalid symbol index")]
+    InvalidSymbolIndex,
+    #[error("Invalid member `{member}`: {error}")]
+    InvalidMember { member: String, error: ElfReadError },
+}
+
+/// Reads the big-endian symbol index, mapping member header offsets to member indices.
+fn read_symbol_index<'data>(
+    data: &'data [u8],
+    width: usize,
+    member_offsets: &HashMap<usize, usize>,
+) -> Result<HashMap<&'data str, usize>, ArchiveReadError> {
+    let invalid = ArchiveReadError::InvalidSymbolIndex;
+    let read_word = |offset: usize| -> Result<usize, ArchiveReadError> {
+        let word = data.get(offset..offset + width).ok_or(invalid.clone())?;
+        let value = word.iter().fold(0u64, |value, &b| value << 8 | b as u64);
+        usize::try_from(value).map_err(|_| invalid.clone())
+    };
+
+    let count = read_word(0)?;
+    let names_offset = count
+        .checked_add(1)
+        .and_then(|words| words.checked_mul(width))
+        .ok_or(invalid.clone())?;
+    let mut names = data
+        .get(names_offset..)
+        .ok_or(invalid.clone())?
+        .split(|&b| b == 0);
+    let mut symbol_index = HashMap::new();
+    for i in 0..count {
+        let member = member_offsets
+            .get(&read_word((i + 1) * width)?)
+            .ok_or(invalid.clone())?;
+        let name = names.next().ok_or(invalid.clone())?;
+        let name = std::str::from_utf8(name).map_err(|_| invalid.clone())?;
+        // Like the archive order, the first definition wins
+        symbol_index.entry(name).or_insert(*member);
+    }
+    Ok(symbol_index)
+}
+
+/// Indexes the symbols defined by each member, for archives without a symbol index.
+fn index_members<'name>(
+    objects: &[InMemoryRelocatableObject<'name>],
+) -> HashMap<&'name str, usize> {
+    let mut symbol_index = HashMap::new();
+    for (index, object) in objects.iter().enumerate() {
+        for (_, symbol) in object.symbol_table.iter() {
+            if symbol.binding == SymbolBinding::Local || symbol.value == SymbolValue::Undefined {
+                continue;
+            }
+            symbol_index.entry(symbol.name).or_insert(index);
+        }
+    }
+    symbol_index
+}
+
+/// Resolves a GNU member name: `name/`, or `/offset` into the long name table.
+fn member_name<'data>(name: &'data [u8], long_names: &'data [u8]) -> Option<&'data str> {
+    let name = match name.strip_prefix(b"/") {
+        Some(offset) => {
+            let offset = std::str::from_utf8(offset).ok()?.parse::<usize>().ok()?;
+            let long_name = long_names.get(offset..)?;
+            let len = long_name.windows(2).position(|w| w == b"/\n")?;
+            &long_name[..len]
+        }
+        None => name.strip_suffix(b"/").unwrap_or(name),
+    };
+    std::str::from_utf8(name).ok()
+}
+
+fn trim_end(bytes: &[u8]) -> &[u8] {
+    let len = bytes.iter().rposition(|&b| b != b' ').map_or(0, |i| i + 1);
+    &bytes[..len]
+}
+
+fn archive_slice<'data>(
+    bytes: &'data [u8],
+    offset: usize,
+    len: usize,
+    what: &'static str,
+) -> Result<&'data [u8], ArchiveReadError> {
+    offset
+        .checked_add(len)
+        .and_then(|end| bytes.get(offset..end))
+        .ok_or(ArchiveReadError::Truncated { what })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        core::models::Symbol,
+        in_memory::{
+            link,
+            models::{EntryPoint, LinkConfig},
+            LinkError, LinkInput,
+        },
+    };
+
+    use super::*;
+
+    const MAIN_O: &[u8] = include_bytes!("../../fixtures/main.o");
+    const LIBSUM_A: &[u8] = include_bytes!("../../fixtures/libsum.a");
+
+    #[test]
+    fn read_libsum_a() {
+        let archive = read_archive(LIBSUM_A).unwrap();
+
+        let names = archive
+            .members
+            .iter()
+            .map(|member| member.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["sum.o", "counter_with_long_name.o"]);
+        assert_eq!(archive.symbol_index.len(), 3);
+        assert_eq!(archive.symbol_index["sum"], 0);
+        assert_eq!(archive.symbol_index["bump"], 1);
+        assert_eq!(archive.symbol_index["counter"], 1);
+    }
+
+    #[test]
+    fn link_needed_members() {
+        let inputs = vec![
+            LinkInput::Object(read_relocatable_object("main.o", MAIN_O).unwrap()),
+            LinkInput::Archive(read_archive(LIBSUM_A).unwrap()),
+        ];
+        let executable = link(
+            inputs,
+            &LinkConfig {
+                entry: EntryPoint::Symbol("main".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert!(executable.symbol_table.get_by_name("sum").is_some());
+        assert!(executable.symbol_table.get_by_name("bump").is_none());
+    }
+
+    #[test]
+    fn parse_members_when_linked() {
+        // Break the ELF magic of `counter_with_long_name.o`
+        let mut bytes = LIBSUM_A.to_vec();
+        let magic = bytes
+            .windows(ELF_MAGIC.len())
+            .enumerate()
+            .filter(|(_, window)| *window == ELF_MAGIC)
+            .map(|(offset, _)| offset)
+            .nth(1)
+            .unwrap();
+        bytes[magic] = 0;
+        let inputs = |undefined| {
+            let mut main = read_relocatable_object("main.o", MAIN_O).unwrap();
+            main.symbol_table.add(Symbol {
+                name: undefined,
+                value: SymbolValue::Undefined,
+                binding: SymbolBinding::Global,
+            });
+            vec![
+                LinkInput::Object(main),
+                LinkInput::Archive(read_archive(&bytes).unwrap()),
+            ]
+        };
+        let config = LinkConfig {
+            entry: EntryPoint::Symbol("main".to_string()),
+            ..Default::default()
+        };
+
+        // Only a link that needs the member reads it
+        assert!(link(inputs("sum"), &config).is_ok());
+        let Err(diagnostics) = link(inputs("bump"), &config) else {
+            panic!("`counter_with_long_name.o` is valid");
+        };
+        assert_eq!(
+            diagnostics[0].error,
+            LinkError::InvalidMember {
+                member: "counter_with_long_name.o".to_string(),
+                error: ElfReadError::InvalidMagic.to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn reject_non_archive() {
+        assert_eq!(
+            read_archive(MAIN_O).err(),
+            Some(ArchiveReadError::InvalidMagic)
+        );
+        assert_eq!(
+            read_archive(&LIBSUM_A[..0x50]).err(),
+            Some(ArchiveReadError::Truncated { what: "member" })
+        );
+    }
+}
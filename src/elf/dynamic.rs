@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+
+use crate::{
+    core::models::{Architecture, LoadableSectionTable, SymbolBinding, SymbolIndex, SymbolValue},
+    in_memory::models::{
+        DynamicRelocationKind, InMemoryExecutableObject, InMemorySectionIndex, SectionKind,
+    },
+};
+
+use super::models::*;
+
+/// A section that the loader reads, placed after the linked image.
+pub struct DynamicSection {
+    pub name: &'static str,
+    pub typ: u32,
+    pub flags: u64,
+    pub address: u64,
+    pub bytes: Vec<u8>,
+    pub entsize: u64,
+    /// The section header index of the related section.
+    pub link: u32,
+    pub info: u32,
+}
+
+/// Lays out the sections that tell the loader how to relocate the image, from the
+/// page after `end`: the dynamic symbols and their lookup tables, which only the
+/// null symbol is in for an executable, the dynamic relocations, then `.dynamic` on
+/// a page of its own since it is writable.
+///
+/// `section_headers` gives the section header index of each output section, and
+/// `first_index` the one of the first section returned.
+pub fn dynamic_sections(
+    executable: &InMemoryExecutableObject,
+    end: u64,
+    page_size: u64,
+    section_headers: &HashMap<InMemorySectionIndex, u32>,
+    first_index: u32,
+) -> Vec<DynamicSection> {
+    let section_table = &executable.section_table;
+    let symbols = DynamicSymbols::new(executable);
+    let mut strings = vec![0];
+    let soname = executable
+        .soname
+        .as_deref()
+        .map(|soname| add_string(&mut strings, soname));
+
+    // Contents that do not depend on where these sections are placed
+    let mut sections = vec![];
+    let section = |name, typ, flags, bytes, entsize| DynamicSection {
+        name,
+        typ,
+        flags,
+        address: 0,
+        bytes,
+        entsize,
+        link: 0,
+        info: 0,
+    };
+    let dynsym = symbols.symbol_table(executable, section_headers, &mut strings);
+    sections.push(section(
+        ".dynsym",
+        SHT_DYNSYM,
+        SHF_ALLOC,
+        dynsym,
+        ElfSymbol::SIZE as u64,
+    ));
+    sections.push(section(
+        ".dynstr",
+        SHT_STRTAB,
+        SHF_ALLOC,
+        strings.clone(),
+        0,
+    ));
+    sections.push(section(
+        ".hash",
+        SHT_HASH,
+        SHF_ALLOC,
+        symbols.hash_table(executable),
+        4,
+    ));
+    sections.push(section(
+        ".gnu.hash",
+        SHT_GNU_HASH,
+        SHF_ALLOC,
+        symbols.gnu_hash_table(executable),
+        0,
+    ));
+    let (rela_dyn, relative_count, rela_plt) = symbols.relocations(executable);
+    sections.push(section(
+        ".rela.dyn",
+        SHT_RELA,
+        SHF_ALLOC,
+        rela_dyn,
+        ElfRela::SIZE as u64,
+    ));
+    if !rela_plt.is_empty() {
+        sections.push(section(
+            ".rela.plt",
+            SHT_RELA,
+            SHF_ALLOC | SHF_INFO_LINK,
+            rela_plt,
+            ElfRela::SIZE as u64,
+        ));
+    }
+
+    // Place them, then point `.dynamic` to them
+    let mut cursor = align_up(end, page_size);
+    for section in &mut sections {
+        section.address = align_up(cursor, 8);
+        cursor = section.address + section.bytes.len() as u64;
+    }
+    let find = |name| sections.iter().position(|section| section.name == name);
+    let address = |name| find(name).map(|i| sections[i].address);
+    let size = |name| find(name).map_or(0, |i| sections[i].bytes.len() as u64);
+    let got_plt = section_table
+        .indices()
+        .find(|&index| section_table.section(index).name == ".got.plt");
+    let mut entries = vec![];
+    if let Some(soname) = soname {
+        entries.push((DT_SONAME, soname as u64));
+    }
+    entries.extend([
+        (DT_HASH, address(".hash").unwrap()),
+        (DT_GNU_HASH, address(".gnu.hash").unwrap()),
+        (DT_SYMTAB, address(".dynsym").unwrap()),
+        (DT_SYMENT, ElfSymbol::SIZE as u64),
+        (DT_STRTAB, address(".dynstr").unwrap()),
+        (DT_STRSZ, size(".dynstr")),
+        (DT_RELA, address(".rela.dyn").unwrap()),
+        (DT_RELASZ, size(".rela.dyn")),
+        (DT_RELAENT, ElfRela::SIZE as u64),
+        (DT_RELACOUNT, relative_count),
+    ]);
+    if let (Some(address), Some(got_plt)) = (address(".rela.plt"), got_plt) {
+        entries.extend([
+            (DT_PLTGOT, section_table.address(got_plt) as u64),
+            (DT_JMPREL, address),
+            (DT_PLTRELSZ, size(".rela.plt")),
+            (DT_PLTREL, DT_RELA),
+        ]);
+    }
+    // The loader makes read-only segments writable while it relocates them
+    let text_relocations = executable.dynamic_relocations.iter().any(|relocation| {
+        section_table.indices().any(|index| {
+            let section = section_table.section(index);
+            let start = section_table.address(index);
+            section.kind != SectionKind::Data
+                && (start..start + section.data.len()).contains(&relocation.address)
+        })
+    });
+    if text_relocations {
+        entries.extend([(DT_TEXTREL, 0), (DT_FLAGS, DF_TEXTREL)]);
+    }
+    if executable.pie && !executable.shared {
+        entries.push((DT_FLAGS_1, DF_1_PIE));
+    }
+    entries.push((DT_NULL, 0));
+    let mut dynamic = vec![];
+    for (tag, value) in entries {
+        ElfDynamic { tag, value }.write(&mut dynamic, executable.endianness);
+    }
+    let mut dynamic = section(
+        ".dynamic",
+        SHT_DYNAMIC,
+        SHF_ALLOC | SHF_WRITE,
+        dynamic,
+        ElfDynamic::SIZE as u64,
+    );
+    dynamic.address = align_up(cursor, page_size);
+
+    // Link the sections to the ones they refer to
+    let index = |name| find(name).map_or(0, |i| first_index + i as u32);
+    let (dynsym, dynstr) = (index(".dynsym"), index(".dynstr"));
+    for section in &mut sections {
+        match section.name {
+            ".dynsym" => {
+                section.link = dynstr;
+                // Only the null symbol is local
+                section.info = 1;
+            }
+            ".hash" | ".gnu.hash" | ".rela.dyn" => section.link = dynsym,
+            ".rela.plt" => {
+                section.link = dynsym;
+                section.info = got_plt.map_or(0, |got_plt| section_headers[&got_plt]);
+            }
+            _ => (),
+        }
+    }
+    dynamic.link = dynstr;
+    sections.push(dynamic);
+    sections
+}
+
+/// The symbols of `.dynsym` after the null symbol: the undefined ones, then the
+/// defined ones sorted by `.gnu.hash` bucket, as lookups through it require.
+struct DynamicSymbols {
+    symbols: Vec<SymbolIndex>,
+    /// The number of undefined symbols.
+    undefined: usize,
+    /// The number of `.gnu.hash` and `.hash` buckets.
+    buckets: u32,
+}
+impl DynamicSymbols {
+    fn new(executable: &InMemoryExecutableObject) -> Self {
+        let symbol_table = &executable.symbol_table;
+        let (mut defined, undefined): (Vec<_>, Vec<_>) =
+            executable.dynamic_symbols.iter().partition(|&&index| {
+                matches!(
+                    symbol_table.get(index).value,
+                    SymbolValue::Defined(_) | SymbolValue::Absolute(_)
+                )
+            });
+        let buckets = (defined.len() as u32).max(1);
+        defined.sort_by_key(|&index| gnu_hash(symbol_table.get(index).name) % buckets);
+        Self {
+            undefined: undefined.len(),
+            symbols: undefined.into_iter().chain(defined).collect(),
+            buckets,
+        }
+    }
+
+    /// The contents of `.dynsym`, adding the names to `strings`.
+    fn symbol_table(
+        &self,
+        executable: &InMemoryExecutableObject,
+        section_headers: &HashMap<InMemorySectionIndex, u32>,
+        strings: &mut Vec<u8>,
+    ) -> Vec<u8> {
+        let section_table = &executable.section_table;
+        let mut out = vec![0; ElfSymbol::SIZE];
+        for &index in &self.symbols {
+            let symbol = executable.symbol_table.get(index);
+            let binding = match symbol.binding {
+                SymbolBinding::Weak => STB_WEAK,
+                _ => STB_GLOBAL,
+            };
+            let (typ, shndx, value, size) = match symbol.value {
+                SymbolValue::Defined(definition) => (
+                    match section_table.section(definition.section).kind {
+                        SectionKind::Text => STT_FUNC,
+                        _ => STT_OBJECT,
+                    },
+                    section_headers[&definition.section] as u16,
+                    (section_table.address(definition.section) + definition.offset) as u64,
+                    definition.size as u64,
+                ),
+                SymbolValue::Absolute(value) => (STT_NOTYPE, SHN_ABS, value as u64, 0),
+                _ => (STT_NOTYPE, SHN_UNDEF, 0, 0),
+            };
+            ElfSymbol {
+                name: add_string(strings, symbol.name),
+                info: binding << 4 | typ,
+                other: 0,
+                shndx,
+                value,
+                size,
+            }
+            .write(&mut out, executable.endianness);
+        }
+        out
+    }
+
+    /// The contents of `.hash`, which older loaders look symbols up through.
+    fn hash_table(&self, executable: &InMemoryExecutableObject) -> Vec<u8> {
+        let mut buckets = vec![0; self.buckets as usize];
+        let mut chains = vec![0; self.symbols.len() + 1];
+        for (i, &index) in self.symbols.iter().enumerate() {
+            let bucket = elf_hash(executable.symbol_table.get(index).name) % self.buckets;
+            chains[i + 1] = buckets[bucket as usize];
+            buckets[bucket as usize] = i as u32 + 1;
+        }
+        [self.buckets, chains.len() as u32]
+            .into_iter()
+            .chain(buckets)
+            .chain(chains)
+            .fold(vec![], |mut out, word| {
+                put_u32(&mut out, word, executable.endianness);
+                out
+            })
+    }
+
+    /// The contents of `.gnu.hash`, with a single Bloom filter word.
+    fn gnu_hash_table(&self, executable: &InMemoryExecutableObject) -> Vec<u8> {
+        const BLOOM_SHIFT: u32 = 6;
+        let symbol_offset = self.undefined as u32 + 1;
+        let hashes = self.symbols[self.undefined..]
+            .iter()
+            .map(|&index| gnu_hash(executable.symbol_table.get(index).name))
+            .collect::<Vec<_>>();
+
+        let mut bloom = 0u64;
+        let mut buckets = vec![0; self.buckets as usize];
+        let mut chains = vec![];
+        for (i, &hash) in hashes.iter().enumerate() {
+            bloom |= 1 << (hash % 64) | 1 << ((hash >> BLOOM_SHIFT) % 64);
+            let bucket = hash % self.buckets;
+            if buckets[bucket as usize] == 0 {
+                buckets[bucket as usize] = symbol_offset + i as u32;
+            }
+            // The low bit ends the chain of a bucket
+            let last = hashes
+                .get(i + 1)
+                .is_none_or(|next| next % self.buckets != bucket);
+            chains.push(hash & !1 | last as u32);
+        }
+
+        let endianness = executable.endianness;
+        let mut out = vec![];
+        for word in [self.buckets, symbol_offset, 1, BLOOM_SHIFT] {
+            put_u32(&mut out, word, endianness);
+        }
+        put_u64(&mut out, bloom, endianness);
+        for word in buckets.into_iter().chain(chains) {
+            put_u32(&mut out, word, endianness);
+        }
+        out
+    }
+
+    /// The contents of `.rela.dyn`, with the relative relocations first, their
+    /// number, and the contents of `.rela.plt`.
+    fn relocations(&self, executable: &InMemoryExecutableObject) -> (Vec<u8>, u64, Vec<u8>) {
+        let positions = self
+            .symbols
+            .iter()
+            .enumerate()
+            .map(|(i, &index)| (index, i as u64 + 1))
+            .collect::<HashMap<_, _>>();
+        let mut relocations = executable.dynamic_relocations.clone();
+        relocations.sort_by_key(|relocation| relocation.kind != DynamicRelocationKind::Relative);
+
+        let (mut rela_dyn, mut rela_plt) = (vec![], vec![]);
+        let mut relative_count = 0;
+        for relocation in relocations {
+            let (symbol, out) = match relocation.kind {
+                DynamicRelocationKind::Relative => {
+                    relative_count += 1;
+                    (None, &mut rela_dyn)
+                }
+                DynamicRelocationKind::Absolute(symbol)
+                | DynamicRelocationKind::GotEntry(symbol) => (Some(symbol), &mut rela_dyn),
+                DynamicRelocationKind::PltEntry(symbol) => (Some(symbol), &mut rela_plt),
+            };
+            let typ = relocation_type(executable.architecture, relocation.kind);
+            ElfRela {
+                offset: relocation.address as u64,
+                info: symbol.map_or(0, |symbol| positions[&symbol] << 32) | typ as u64,
+                addend: relocation.addend as i64,
+            }
+            .write(out, executable.endianness);
+        }
+        (rela_dyn, relative_count, rela_plt)
+    }
+}
+
+/// The relocation type of a dynamic relocation. RISC-V has no type for GOT entries,
+/// which are filled in like any address-sized word.
+fn relocation_type(architecture: Architecture, kind: DynamicRelocationKind) -> u32 {
+    use DynamicRelocationKind::*;
+    match (architecture, kind) {
+        (Architecture::X86_64, Relative) => R_X86_64_RELATIVE,
+        (Architecture::X86_64, Absolute(_)) => R_X86_64_64,
+        (Architecture::X86_64, GotEntry(_)) => R_X86_64_GLOB_DAT,
+        (Architecture::X86_64, PltEntry(_)) => R_X86_64_JUMP_SLOT,
+        (Architecture::Aarch64, Relative) => R_AARCH64_RELATIVE,
+        (Architecture::Aarch64, Absolute(_)) => R_AARCH64_ABS64,
+        (Architecture::Aarch64, GotEntry(_)) => R_AARCH64_GLOB_DAT,
+        (Architecture::Aarch64, PltEntry(_)) => R_AARCH64_JUMP_SLOT,
+        (Architecture::RiscV, Relative) => R_RISCV_RELATIVE,
+        (Architecture::RiscV, Absolute(_) | GotEntry(_)) => R_RISCV_64,
+        (Architecture::RiscV, PltEntry(_)) => R_RISCV_JUMP_SLOT,
+    }
+}
+
+fn add_string(strings: &mut Vec<u8>, string: &str) -> u32 {
+    let offset = strings.len() as u32;
+    strings.extend_from_slice(string.as_bytes());
+    strings.push(0);
+    offset
+}
+
+/// The hash function of `.hash`.
+fn elf_hash(name: &str) -> u32 {
+    name.bytes().fold(0u32, |hash, byte| {
+        let hash = (hash << 4).wrapping_add(byte as u32);
+        let high = hash & 0xf000_0000;
+        (hash ^ (high >> 24)) & !high
+    })
+}
+
+/// The hash function of `.gnu.hash`.
+fn gnu_hash(name: &str) -> u32 {
+    name.bytes().fold(5381u32, |hash, byte| {
+        hash.wrapping_mul(33).wrapping_add(byte as u32)
+    })
+}
+
+fn align_up(value: u64, alignment: u64) -> u64 {
+    value.div_ceil(alignment) * alignment
+}
@@ -0,0 +1,9 @@
+pub use archive::*;
+pub use read::*;
+pub use write::*;
+
+mod archive;
+mod dynamic;
+pub mod models;
+mod read;
+mod write;
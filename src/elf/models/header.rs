@@ -0,0 +1,322 @@
+use crate::{elf::ElfReadError, in_memory::models::Endianness};
+
+use super::{EI_DATA, ELFDATA2MSB};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElfHeader {
+    pub ident: [u8; 16],
+    pub typ: u16,
+    pub machine: u16,
+    pub version: u32,
+    pub entry: u64,
+    pub phoff: u64,
+    pub shoff: u64,
+    pub flags: u32,
+    pub ehsize: u16,
+    pub phentsize: u16,
+    pub phnum: u16,
+    pub shentsize: u16,
+    pub shnum: u16,
+    pub shstrndx: u16,
+}
+impl ElfHeader {
+    pub const SIZE: usize = 64;
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, ElfReadError> {
+        let bytes = slice(bytes, 0, Self::SIZE as u64, "ELF header")?;
+        let endianness = endianness(bytes[EI_DATA]);
+        Ok(Self {
+            ident: bytes[..16].try_into().unwrap(),
+            typ: u16_at(bytes, 16, endianness),
+            machine: u16_at(bytes, 18, endianness),
+            version: u32_at(bytes, 20, endianness),
+            entry: u64_at(bytes, 24, endianness),
+            phoff: u64_at(bytes, 32, endianness),
+            shoff: u64_at(bytes, 40, endianness),
+            flags: u32_at(bytes, 48, endianness),
+            ehsize: u16_at(bytes, 52, endianness),
+            phentsize: u16_at(bytes, 54, endianness),
+            phnum: u16_at(bytes, 56, endianness),
+            shentsize: u16_at(bytes, 58, endianness),
+            shnum: u16_at(bytes, 60, endianness),
+            shstrndx: u16_at(bytes, 62, endianness),
+        })
+    }
+
+    /// The byte order of the fields of the file, as named by its identification.
+    pub fn endianness(&self) -> Endianness {
+        endianness(self.ident[EI_DATA])
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        let endianness = self.endianness();
+        out.extend_from_slice(&self.ident);
+        put_u16(out, self.typ, endianness);
+        put_u16(out, self.machine, endianness);
+        put_u32(out, self.version, endianness);
+        put_u64(out, self.entry, endianness);
+        put_u64(out, self.phoff, endianness);
+        put_u64(out, self.shoff, endianness);
+        put_u32(out, self.flags, endianness);
+        put_u16(out, self.ehsize, endianness);
+        put_u16(out, self.phentsize, endianness);
+        put_u16(out, self.phnum, endianness);
+        put_u16(out, self.shentsize, endianness);
+        put_u16(out, self.shnum, endianness);
+        put_u16(out, self.shstrndx, endianness);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElfProgramHeader {
+    pub typ: u32,
+    pub flags: u32,
+    pub offset: u64,
+    pub vaddr: u64,
+    pub paddr: u64,
+    pub filesz: u64,
+    pub memsz: u64,
+    pub align: u64,
+}
+impl ElfProgramHeader {
+    pub const SIZE: usize = 56;
+
+    pub fn parse(bytes: &[u8], endianness: Endianness) -> Result<Self, ElfReadError> {
+        let bytes = slice(bytes, 0, Self::SIZE as u64, "program header")?;
+        Ok(Self {
+            typ: u32_at(bytes, 0, endianness),
+            flags: u32_at(bytes, 4, endianness),
+            offset: u64_at(bytes, 8, endianness),
+            vaddr: u64_at(bytes, 16, endianness),
+            paddr: u64_at(bytes, 24, endianness),
+            filesz: u64_at(bytes, 32, endianness),
+            memsz: u64_at(bytes, 40, endianness),
+            align: u64_at(bytes, 48, endianness),
+        })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>, endianness: Endianness) {
+        put_u32(out, self.typ, endianness);
+        put_u32(out, self.flags, endianness);
+        put_u64(out, self.offset, endianness);
+        put_u64(out, self.vaddr, endianness);
+        put_u64(out, self.paddr, endianness);
+        put_u64(out, self.filesz, endianness);
+        put_u64(out, self.memsz, endianness);
+        put_u64(out, self.align, endianness);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElfSectionHeader {
+    pub name: u32,
+    pub typ: u32,
+    pub flags: u64,
+    pub addr: u64,
+    pub offset: u64,
+    pub size: u64,
+    pub link: u32,
+    pub info: u32,
+    pub addralign: u64,
+    pub entsize: u64,
+}
+impl ElfSectionHeader {
+    pub const SIZE: usize = 64;
+
+    pub fn parse(bytes: &[u8], endianness: Endianness) -> Result<Self, ElfReadError> {
+        let bytes = slice(bytes, 0, Self::SIZE as u64, "section header")?;
+        Ok(Self {
+            name: u32_at(bytes, 0, endianness),
+            typ: u32_at(bytes, 4, endianness),
+            flags: u64_at(bytes, 8, endianness),
+            addr: u64_at(bytes, 16, endianness),
+            offset: u64_at(bytes, 24, endianness),
+            size: u64_at(bytes, 32, endianness),
+            link: u32_at(bytes, 40, endianness),
+            info: u32_at(bytes, 44, endianness),
+            addralign: u64_at(bytes, 48, endianness),
+            entsize: u64_at(bytes, 56, endianness),
+        })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>, endianness: Endianness) {
+        put_u32(out, self.name, endianness);
+        put_u32(out, self.typ, endianness);
+        put_u64(out, self.flags, endianness);
+        put_u64(out, self.addr, endianness);
+        put_u64(out, self.offset, endianness);
+        put_u64(out, self.size, endianness);
+        put_u32(out, self.link, endianness);
+        put_u32(out, self.info, endianness);
+        put_u64(out, self.addralign, endianness);
+        put_u64(out, self.entsize, endianness);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElfSymbol {
+    pub name: u32,
+    pub info: u8,
+    pub other: u8,
+    pub shndx: u16,
+    pub value: u64,
+    pub size: u64,
+}
+impl ElfSymbol {
+    pub const SIZE: usize = 24;
+
+    pub fn parse(bytes: &[u8], endianness: Endianness) -> Result<Self, ElfReadError> {
+        let bytes = slice(bytes, 0, Self::SIZE as u64, "symbol")?;
+        Ok(Self {
+            name: u32_at(bytes, 0, endianness),
+            info: bytes[4],
+            other: bytes[5],
+            shndx: u16_at(bytes, 6, endianness),
+            value: u64_at(bytes, 8, endianness),
+            size: u64_at(bytes, 16, endianness),
+        })
+    }
+
+    pub fn binding(&self) -> u8 {
+        self.info >> 4
+    }
+
+    pub fn typ(&self) -> u8 {
+        self.info & 0xf
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>, endianness: Endianness) {
+        put_u32(out, self.name, endianness);
+        out.push(self.info);
+        out.push(self.other);
+        put_u16(out, self.shndx, endianness);
+        put_u64(out, self.value, endianness);
+        put_u64(out, self.size, endianness);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElfRela {
+    pub offset: u64,
+    pub info: u64,
+    pub addend: i64,
+}
+impl ElfRela {
+    pub const SIZE: usize = 24;
+
+    pub fn parse(bytes: &[u8], endianness: Endianness) -> Result<Self, ElfReadError> {
+        let bytes = slice(bytes, 0, Self::SIZE as u64, "relocation")?;
+        Ok(Self {
+            offset: u64_at(bytes, 0, endianness),
+            info: u64_at(bytes, 8, endianness),
+            addend: u64_at(bytes, 16, endianness) as i64,
+        })
+    }
+
+    pub fn symbol(&self) -> u32 {
+        (self.info >> 32) as u32
+    }
+
+    pub fn typ(&self) -> u32 {
+        (self.info & 0xffff_ffff) as u32
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>, endianness: Endianness) {
+        put_u64(out, self.offset, endianness);
+        put_u64(out, self.info, endianness);
+        put_u64(out, self.addend as u64, endianness);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElfDynamic {
+    pub tag: u64,
+    pub value: u64,
+}
+impl ElfDynamic {
+    pub const SIZE: usize = 16;
+
+    pub fn parse(bytes: &[u8], endianness: Endianness) -> Result<Self, ElfReadError> {
+        let bytes = slice(bytes, 0, Self::SIZE as u64, "dynamic entry")?;
+        Ok(Self {
+            tag: u64_at(bytes, 0, endianness),
+            value: u64_at(bytes, 8, endianness),
+        })
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>, endianness: Endianness) {
+        put_u64(out, self.tag, endianness);
+        put_u64(out, self.value, endianness);
+    }
+}
+
+/// Returns `bytes[offset..offset + len]`, or an error naming `what` was cut off.
+pub fn slice<'data>(
+    bytes: &'data [u8],
+    offset: u64,
+    len: u64,
+    what: &'static str,
+) -> Result<&'data [u8], ElfReadError> {
+    let start = usize::try_from(offset).map_err(|_| ElfReadError::Truncated { what })?;
+    let len = usize::try_from(len).map_err(|_| ElfReadError::Truncated { what })?;
+    let end = start
+        .checked_add(len)
+        .ok_or(ElfReadError::Truncated { what })?;
+    bytes
+        .get(start..end)
+        .ok_or(ElfReadError::Truncated { what })
+}
+
+/// Any encoding other than big-endian is rejected by the reader before fields are
+/// read, so it is taken to be little-endian.
+fn endianness(encoding: u8) -> Endianness {
+    match encoding {
+        ELFDATA2MSB => Endianness::Big,
+        _ => Endianness::Little,
+    }
+}
+
+fn u16_at(bytes: &[u8], offset: usize, endianness: Endianness) -> u16 {
+    let field = bytes[offset..offset + 2].try_into().unwrap();
+    match endianness {
+        Endianness::Little => u16::from_le_bytes(field),
+        Endianness::Big => u16::from_be_bytes(field),
+    }
+}
+
+fn u32_at(bytes: &[u8], offset: usize, endianness: Endianness) -> u32 {
+    let field = bytes[offset..offset + 4].try_into().unwrap();
+    match endianness {
+        Endianness::Little => u32::from_le_bytes(field),
+        Endianness::Big => u32::from_be_bytes(field),
+    }
+}
+
+fn u64_at(bytes: &[u8], offset: usize, endianness: Endianness) -> u64 {
+    let field = bytes[offset..offset + 8].try_into().unwrap();
+    match endianness {
+        Endianness::Little => u64::from_le_bytes(field),
+        Endianness::Big => u64::from_be_bytes(field),
+    }
+}
+
+pub fn put_u16(out: &mut Vec<u8>, value: u16, endianness: Endianness) {
+    match endianness {
+        Endianness::Little => out.extend_from_slice(&value.to_le_bytes()),
+        Endianness::Big => out.extend_from_slice(&value.to_be_bytes()),
+    }
+}
+
+pub fn put_u32(out: &mut Vec<u8>, value: u32, endianness: Endianness) {
+    match endianness {
+        Endianness::Little => out.extend_from_slice(&value.to_le_bytes()),
+        Endianness::Big => out.extend_from_slice(&value.to_be_bytes()),
+    }
+}
+
+pub fn put_u64(out: &mut Vec<u8>, value: u64, endianness: Endianness) {
+    match endianness {
+        Endianness::Little => out.extend_from_slice(&value.to_le_bytes()),
+        Endianness::Big => out.extend_from_slice(&value.to_be_bytes()),
+    }
+}
@@ -0,0 +1,5 @@
+pub use constants::*;
+pub use header::*;
+
+mod constants;
+mod header;
@@ -0,0 +1,641 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::{
+    core::models::{
+        Aarch64RelocationType, Relocation, RelocationType, RiscVRelocationType, Symbol,
+        SymbolBinding, SymbolDefinition, SymbolIndex, SymbolTable, SymbolValue,
+        X86_64RelocationType,
+    },
+    in_memory::models::{
+        Endianness, InMemoryLoadableSectionTable, InMemoryRelocatableObject, SectionKind,
+    },
+};
+
+use super::models::*;
+
+/// Reads an ELF64 relocatable object file (`.o`) of either byte order, identified by
+/// `name` in diagnostics.
+///
+/// Only allocated sections are kept; the symbol and relocation tables are
+/// translated to refer to them.
+pub fn read_relocatable_object<'name>(
+    name: &'name str,
+    bytes: &'name [u8],
+) -> Result<InMemoryRelocatableObject<'name>, ElfReadError> {
+    let header = read_header(bytes)?;
+    let endianness = header.endianness();
+    let section_headers = read_section_headers(bytes, &header)?;
+    let section_names = section_headers
+        .get(section_string_table_index(&header, &section_headers))
+        .ok_or(ElfReadError::InvalidSectionIndex {
+            index: header.shstrndx as usize,
+        })?;
+    let section_names = slice(
+        bytes,
+        section_names.offset,
+        section_names.size,
+        "section names",
+    )?;
+
+    let mut section_table = InMemoryLoadableSectionTable::new();
+    let mut symbol_table = SymbolTable::new();
+    let mut references = Vec::new();
+
+    // Load allocated sections
+    let mut loaded_sections = HashMap::new();
+    for (elf_index, section_header) in section_headers.iter().enumerate() {
+        if !is_loadable(section_header) {
+            continue;
+        }
+        let name = string_at(section_names, section_header.name)?;
+        let kind = section_kind(section_header);
+        let alignment = to_usize(section_header.addralign.max(1), "section")?;
+        let index = match section_header.typ {
+            SHT_NOBITS => section_table.add_zero_fill_section(
+                name,
+                kind,
+                alignment,
+                to_usize(section_header.size, "section")?,
+            ),
+            _ => section_table.add_section(
+                name,
+                kind,
+                alignment,
+                slice(bytes, section_header.offset, section_header.size, "section")?.to_vec(),
+            ),
+        };
+        let section = section_table.section_mut(index);
+        section.keep = is_kept(section_header);
+        section.unwind = is_unwind(name, section_header);
+        loaded_sections.insert(elf_index, index);
+    }
+
+    // Load symbols
+    let mut loaded_symbols = HashMap::new();
+    if let Some(symbol_table_header) = section_headers.iter().find(|h| h.typ == SHT_SYMTAB) {
+        let string_table = section_headers
+            .get(symbol_table_header.link as usize)
+            .ok_or(ElfReadError::InvalidSectionIndex {
+                index: symbol_table_header.link as usize,
+            })?;
+        let string_table = slice(
+            bytes,
+            string_table.offset,
+            string_table.size,
+            "string table",
+        )?;
+        let extended_indices = read_extended_section_indices(bytes, &section_headers, endianness)?;
+        let symbols = slice(
+            bytes,
+            symbol_table_header.offset,
+            symbol_table_header.size,
+            "symbol table",
+        )?;
+
+        // Entry 0 is the reserved null symbol
+        for (elf_index, entry) in symbols.chunks_exact(ElfSymbol::SIZE).enumerate().skip(1) {
+            let elf_symbol = ElfSymbol::parse(entry, endianness)?;
+            if elf_symbol.typ() == STT_FILE {
+                continue;
+            }
+            let section_index = match elf_symbol.shndx {
+                SHN_XINDEX => *extended_indices
+                    .as_ref()
+                    .and_then(|indices| indices.get(elf_index))
+                    .ok_or(ElfReadError::InvalidSymbol { index: elf_index })?
+                    as usize,
+                shndx => shndx as usize,
+            };
+            let value = match elf_symbol.shndx {
+                SHN_UNDEF => SymbolValue::Undefined,
+                SHN_COMMON => SymbolValue::Common {
+                    size: to_usize(elf_symbol.size, "symbol")?,
+                    // The value of a common symbol is its alignment
+                    alignment: to_usize(elf_symbol.value.max(1), "symbol")?,
+                },
+                SHN_ABS => SymbolValue::Absolute(to_usize(elf_symbol.value, "symbol")?),
+                _ => {
+                    // Symbols of sections we do not load (e.g. debug info) are dropped
+                    let Some(section) = loaded_sections.get(&section_index) else {
+                        continue;
+                    };
+                    SymbolValue::Defined(SymbolDefinition {
+                        section: *section,
+                        offset: to_usize(elf_symbol.value, "symbol")?,
+                        size: to_usize(elf_symbol.size, "symbol")?,
+                    })
+                }
+            };
+            let name = match elf_symbol.typ() {
+                // Section symbols are nameless; refer to them by the section name
+                STT_SECTION => string_at(section_names, section_headers[section_index].name)?,
+                _ => string_at(string_table, elf_symbol.name)?,
+            };
+            let binding = match elf_symbol.binding() {
+                STB_LOCAL => SymbolBinding::Local,
+                STB_WEAK => SymbolBinding::Weak,
+                _ => SymbolBinding::Global,
+            };
+            let index = symbol_table.add(Symbol {
+                name,
+                value,
+                binding,
+            });
+            loaded_symbols.insert(elf_index, index);
+        }
+    }
+
+    // Load relocations of allocated sections
+    for section_header in section_headers.iter() {
+        match section_header.typ {
+            SHT_RELA => (),
+            SHT_REL => {
+                return Err(ElfReadError::UnsupportedRelocationFormat);
+            }
+            _ => continue,
+        }
+        let Some(section) = loaded_sections.get(&(section_header.info as usize)) else {
+            continue;
+        };
+        let entries = slice(
+            bytes,
+            section_header.offset,
+            section_header.size,
+            "relocation table",
+        )?;
+        for entry in entries.chunks_exact(ElfRela::SIZE) {
+            let rela = ElfRela::parse(entry, endianness)?;
+            let Some(typ) = relocation_type(header.machine, rela.typ())? else {
+                continue;
+            };
+            let symbol = relocation_symbol(&loaded_symbols, rela.symbol() as usize)?;
+            references.push(Relocation {
+                offset: to_usize(rela.offset, "relocation")?,
+                section: *section,
+                typ,
+                symbol,
+                addend: rela.addend as isize,
+            });
+        }
+    }
+
+    Ok(InMemoryRelocatableObject {
+        name,
+        section_table,
+        symbol_table,
+        references,
+    })
+}
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum ElfReadError {
+    #[error("Truncated {what}")]
+    Truncated { what: &'static str },
+    #[error("Not an ELF file")]
+    InvalidMagic,
+    #[error("Unsupported ELF class {class}")]
+    UnsupportedClass { class: u8 },
+    #[error("Unsupported ELF data encoding {encoding}")]
+    UnsupportedEncoding { encoding: u8 },
+    #[error("Unsupported ELF file type {typ}")]
+    UnsupportedFileType { typ: u16 },
+    #[error("Unsupported machine {machine}")]
+    UnsupportedMachine { machine: u16 },
+    #[error("Unsupported entry size {size} for {what}")]
+    UnsupportedEntrySize { what: &'static str, size: u64 },
+    #[error("Invalid section index {index}")]
+    InvalidSectionIndex { index: usize },
+    #[error("Invalid string table offset {offset}")]
+    InvalidString { offset: u32 },
+    #[error("Invalid symbol {index}")]
+    InvalidSymbol { index: usize },
+    #[error("Unsupported relocation type {typ}")]
+    UnsupportedRelocationType { typ: u32 },
+    #[error("Implicit-addend (REL) relocations are unsupported")]
+    UnsupportedRelocationFormat,
+}
+
+fn read_header(bytes: &[u8]) -> Result<ElfHeader, ElfReadError> {
+    let header = ElfHeader::parse(bytes)?;
+    if header.ident[..4] != ELF_MAGIC {
+        return Err(ElfReadError::InvalidMagic);
+    }
+    if header.ident[EI_CLASS] != ELFCLASS64 {
+        return Err(ElfReadError::UnsupportedClass {
+            class: header.ident[EI_CLASS],
+        });
+    }
+    if !matches!(header.ident[EI_DATA], ELFDATA2LSB | ELFDATA2MSB) {
+        return Err(ElfReadError::UnsupportedEncoding {
+            encoding: header.ident[EI_DATA],
+        });
+    }
+    if header.typ != ET_REL {
+        return Err(ElfReadError::UnsupportedFileType { typ: header.typ });
+    }
+    if !matches!(header.machine, EM_X86_64 | EM_AARCH64 | EM_RISCV) {
+        return Err(ElfReadError::UnsupportedMachine {
+            machine: header.machine,
+        });
+    }
+    if header.shentsize as usize != ElfSectionHeader::SIZE {
+        return Err(ElfReadError::UnsupportedEntrySize {
+            what: "section header",
+            size: header.shentsize as u64,
+        });
+    }
+    Ok(header)
+}
+
+fn read_section_headers(
+    bytes: &[u8],
+    header: &ElfHeader,
+) -> Result<Vec<ElfSectionHeader>, ElfReadError> {
+    if header.shoff == 0 {
+        return Ok(vec![]);
+    }
+    let endianness = header.endianness();
+    let first = ElfSectionHeader::parse(
+        slice(
+            bytes,
+            header.shoff,
+            ElfSectionHeader::SIZE as u64,
+            "section header",
+        )?,
+        endianness,
+    )?;
+
+    // A count of zero means the real count is stored in the first section header
+    let count = match header.shnum {
+        0 => first.size,
+        count => count as u64,
+    };
+    let table = slice(
+        bytes,
+        header.shoff,
+        count.saturating_mul(ElfSectionHeader::SIZE as u64),
+        "section header table",
+    )?;
+    table
+        .chunks_exact(ElfSectionHeader::SIZE)
+        .map(|entry| ElfSectionHeader::parse(entry, endianness))
+        .collect()
+}
+
+fn section_string_table_index(header: &ElfHeader, section_headers: &[ElfSectionHeader]) -> usize {
+    match header.shstrndx {
+        SHN_XINDEX => section_headers
+            .first()
+            .map_or(0, |first| first.link as usize),
+        index => index as usize,
+    }
+}
+
+fn read_extended_section_indices<'data>(
+    bytes: &'data [u8],
+    section_headers: &[ElfSectionHeader],
+    endianness: Endianness,
+) -> Result<Option<Vec<u32>>, ElfReadError> {
+    let Some(header) = section_headers.iter().find(|h| h.typ == SHT_SYMTAB_SHNDX) else {
+        return Ok(None);
+    };
+    let table: &'data [u8] = slice(
+        bytes,
+        header.offset,
+        header.size,
+        "extended section indices",
+    )?;
+    Ok(Some(
+        table
+            .chunks_exact(4)
+            .map(|entry| endianness.read(entry) as u32)
+            .collect(),
+    ))
+}
+
+fn is_loadable(section_header: &ElfSectionHeader) -> bool {
+    if section_header.flags & SHF_ALLOC == 0 {
+        return false;
+    }
+    matches!(
+        section_header.typ,
+        SHT_PROGBITS
+            | SHT_NOBITS
+            | SHT_INIT_ARRAY
+            | SHT_FINI_ARRAY
+            | SHT_PREINIT_ARRAY
+            | SHT_X86_64_UNWIND
+    )
+}
+
+/// Whether garbage collection must keep the section: constructors and destructors
+/// are only referenced by the runtime.
+fn is_kept(section_header: &ElfSectionHeader) -> bool {
+    section_header.flags & SHF_GNU_RETAIN != 0
+        || matches!(
+            section_header.typ,
+            SHT_INIT_ARRAY | SHT_FINI_ARRAY | SHT_PREINIT_ARRAY
+        )
+}
+
+/// Whether the section holds unwind tables, which only x86-64 gives a section type.
+fn is_unwind(name: &str, section_header: &ElfSectionHeader) -> bool {
+    section_header.typ == SHT_X86_64_UNWIND || name == ".eh_frame"
+}
+
+fn section_kind(section_header: &ElfSectionHeader) -> SectionKind {
+    if section_header.flags & SHF_EXECINSTR != 0 {
+        SectionKind::Text
+    } else if section_header.flags & SHF_WRITE != 0 {
+        SectionKind::Data
+    } else {
+        SectionKind::ReadOnlyData
+    }
+}
+
+fn relocation_type(machine: u16, typ: u32) -> Result<Option<RelocationType>, ElfReadError> {
+    match machine {
+        EM_AARCH64 => aarch64_relocation_type(typ),
+        EM_RISCV => riscv_relocation_type(typ),
+        _ => x86_64_relocation_type(typ),
+    }
+}
+
+fn x86_64_relocation_type(typ: u32) -> Result<Option<RelocationType>, ElfReadError> {
+    use X86_64RelocationType::*;
+    let typ = match typ {
+        R_X86_64_NONE => return Ok(None),
+        R_X86_64_64 => Direct64,
+        R_X86_64_PC32 => Pc32,
+        R_X86_64_GOT32 => Got32,
+        R_X86_64_PLT32 => Plt32,
+        R_X86_64_GOTPCREL => GotPcRel,
+        R_X86_64_32 => Direct32,
+        R_X86_64_32S => Direct32Signed,
+        R_X86_64_16 => Direct16,
+        R_X86_64_PC16 => Pc16,
+        R_X86_64_8 => Direct8,
+        R_X86_64_PC8 => Pc8,
+        R_X86_64_PC64 => Pc64,
+        R_X86_64_GOTOFF64 => GotOff64,
+        R_X86_64_GOTPC32 => GotPc32,
+        R_X86_64_GOT64 => Got64,
+        R_X86_64_GOTPCREL64 => GotPcRel64,
+        R_X86_64_GOTPC64 => GotPc64,
+        R_X86_64_GOTPLT64 => GotPlt64,
+        R_X86_64_PLTOFF64 => PltOff64,
+        R_X86_64_SIZE32 => Size32,
+        R_X86_64_SIZE64 => Size64,
+        R_X86_64_GOTPCRELX => GotPcRelX,
+        R_X86_64_REX_GOTPCRELX => RexGotPcRelX,
+        _ => return Err(ElfReadError::UnsupportedRelocationType { typ }),
+    };
+    Ok(Some(RelocationType::X86_64(typ)))
+}
+
+fn aarch64_relocation_type(typ: u32) -> Result<Option<RelocationType>, ElfReadError> {
+    use Aarch64RelocationType::*;
+    let typ = match typ {
+        R_AARCH64_NONE => return Ok(None),
+        R_AARCH64_ABS64 => Abs64,
+        R_AARCH64_ABS32 => Abs32,
+        R_AARCH64_ABS16 => Abs16,
+        R_AARCH64_PREL64 => Prel64,
+        R_AARCH64_PREL32 => Prel32,
+        R_AARCH64_PREL16 => Prel16,
+        R_AARCH64_MOVW_UABS_G0 => MovwUabsG0,
+        R_AARCH64_MOVW_UABS_G0_NC => MovwUabsG0Nc,
+        R_AARCH64_MOVW_UABS_G1 => MovwUabsG1,
+        R_AARCH64_MOVW_UABS_G1_NC => MovwUabsG1Nc,
+        R_AARCH64_MOVW_UABS_G2 => MovwUabsG2,
+        R_AARCH64_MOVW_UABS_G2_NC => MovwUabsG2Nc,
+        R_AARCH64_MOVW_UABS_G3 => MovwUabsG3,
+        R_AARCH64_LD_PREL_LO19 => LdPrelLo19,
+        R_AARCH64_ADR_PREL_LO21 => AdrPrelLo21,
+        R_AARCH64_ADR_PREL_PG_HI21 => AdrPrelPgHi21,
+        R_AARCH64_ADR_PREL_PG_HI21_NC => AdrPrelPgHi21Nc,
+        R_AARCH64_ADD_ABS_LO12_NC => AddAbsLo12Nc,
+        R_AARCH64_LDST8_ABS_LO12_NC => Ldst8AbsLo12Nc,
+        R_AARCH64_LDST16_ABS_LO12_NC => Ldst16AbsLo12Nc,
+        R_AARCH64_LDST32_ABS_LO12_NC => Ldst32AbsLo12Nc,
+        R_AARCH64_LDST64_ABS_LO12_NC => Ldst64AbsLo12Nc,
+        R_AARCH64_LDST128_ABS_LO12_NC => Ldst128AbsLo12Nc,
+        R_AARCH64_TSTBR14 => Tstbr14,
+        R_AARCH64_CONDBR19 => Condbr19,
+        R_AARCH64_JUMP26 => Jump26,
+        R_AARCH64_CALL26 => Call26,
+        _ => return Err(ElfReadError::UnsupportedRelocationType { typ }),
+    };
+    Ok(Some(RelocationType::Aarch64(typ)))
+}
+
+fn riscv_relocation_type(typ: u32) -> Result<Option<RelocationType>, ElfReadError> {
+    use RiscVRelocationType::*;
+    let typ = match typ {
+        // Sections are not relaxed, so the hints for it are dropped
+        R_RISCV_NONE | R_RISCV_ALIGN | R_RISCV_RELAX => return Ok(None),
+        R_RISCV_32 => Direct32,
+        R_RISCV_64 => Direct64,
+        R_RISCV_BRANCH => Branch,
+        R_RISCV_JAL => Jal,
+        R_RISCV_CALL => Call,
+        R_RISCV_CALL_PLT => CallPlt,
+        R_RISCV_GOT_HI20 => GotHi20,
+        R_RISCV_PCREL_HI20 => PcrelHi20,
+        R_RISCV_PCREL_LO12_I => PcrelLo12I,
+        R_RISCV_PCREL_LO12_S => PcrelLo12S,
+        R_RISCV_HI20 => Hi20,
+        R_RISCV_LO12_I => Lo12I,
+        R_RISCV_LO12_S => Lo12S,
+        R_RISCV_ADD8 => Add8,
+        R_RISCV_ADD16 => Add16,
+        R_RISCV_ADD32 => Add32,
+        R_RISCV_ADD64 => Add64,
+        R_RISCV_SUB8 => Sub8,
+        R_RISCV_SUB16 => Sub16,
+        R_RISCV_SUB32 => Sub32,
+        R_RISCV_SUB64 => Sub64,
+        R_RISCV_RVC_BRANCH => RvcBranch,
+        R_RISCV_RVC_JUMP => RvcJump,
+        R_RISCV_SET8 => Set8,
+        R_RISCV_SET16 => Set16,
+        R_RISCV_SET32 => Set32,
+        R_RISCV_32_PCREL => Pcrel32,
+        _ => return Err(ElfReadError::UnsupportedRelocationType { typ }),
+    };
+    Ok(Some(RelocationType::RiscV(typ)))
+}
+
+fn relocation_symbol(
+    loaded_symbols: &HashMap<usize, SymbolIndex>,
+    elf_index: usize,
+) -> Result<SymbolIndex, ElfReadError> {
+    loaded_symbols
+        .get(&elf_index)
+        .copied()
+        .ok_or(ElfReadError::InvalidSymbol { index: elf_index })
+}
+
+fn string_at(string_table: &[u8], offset: u32) -> Result<&str, ElfReadError> {
+    let invalid = ElfReadError::InvalidString { offset };
+    let bytes = string_table.get(offset as usize..).ok_or(invalid.clone())?;
+    let len = bytes.iter().position(|&b| b == 0).ok_or(invalid.clone())?;
+    std::str::from_utf8(&bytes[..len]).map_err(|_| invalid)
+}
+
+fn to_usize(value: u64, what: &'static str) -> Result<usize, ElfReadError> {
+    usize::try_from(value).map_err(|_| ElfReadError::Truncated { what })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        core::models::{RelocationType, SymbolValue},
+        in_memory::{
+            link,
+            models::{EntryPoint, LinkConfig},
+            LinkInput,
+        },
+    };
+
+    use super::*;
+
+    const MAIN_O: &[u8] = include_bytes!("../../fixtures/main.o");
+    const SUM_O: &[u8] = include_bytes!("../../fixtures/sum.o");
+    const RELOCATIONS_O: &[u8] = include_bytes!("../../fixtures/relocations.o");
+    const ABSOLUTE_O: &[u8] = include_bytes!("../../fixtures/absolute.o");
+
+    #[test]
+    fn read_main_o() {
+        let object = read_relocatable_object("main.o", MAIN_O).unwrap();
+
+        let sections = object
+            .section_table
+            .sections()
+            .map(|section| (section.name.as_str(), section.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sections,
+            vec![
+                (".text", SectionKind::Text),
+                (".data", SectionKind::Data),
+                (".bss", SectionKind::Data),
+            ]
+        );
+
+        let symbols = object.symbol_table.into_iter().collect::<Vec<_>>();
+        let names = symbols.iter().map(|(_, s)| s.name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["main", "array", "sum"]);
+        assert_eq!(symbols[2].1.value, SymbolValue::Undefined);
+
+        assert_eq!(object.references.len(), 2);
+        assert_eq!(object.references[0].offset, 0xa);
+        assert_eq!(
+            object.references[0].typ,
+            RelocationType::X86_64(X86_64RelocationType::Direct32)
+        );
+        assert_eq!(object.references[0].symbol, symbols[1].0);
+        assert_eq!(object.references[1].offset, 0xf);
+        assert_eq!(
+            object.references[1].typ,
+            RelocationType::X86_64(X86_64RelocationType::Plt32)
+        );
+        assert_eq!(object.references[1].addend, -4);
+        assert_eq!(object.references[1].symbol, symbols[2].0);
+    }
+
+    #[test]
+    fn read_absolute_symbols() {
+        let object = read_relocatable_object("absolute.o", ABSOLUTE_O).unwrap();
+
+        let symbols = object
+            .symbol_table
+            .iter()
+            .map(|(_, symbol)| (symbol.name, symbol.binding, symbol.value))
+            .filter(|&(_, _, value)| matches!(value, SymbolValue::Absolute(_)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            symbols,
+            vec![
+                ("limit", SymbolBinding::Local, SymbolValue::Absolute(0x1234)),
+                ("answer", SymbolBinding::Global, SymbolValue::Absolute(42)),
+            ]
+        );
+    }
+
+    #[test]
+    fn link_main_sum() {
+        let objects = vec![
+            LinkInput::Object(read_relocatable_object("main.o", MAIN_O).unwrap()),
+            LinkInput::Object(read_relocatable_object("sum.o", SUM_O).unwrap()),
+        ];
+        let config = LinkConfig {
+            image_base: 0,
+            page_size: None,
+            entry: EntryPoint::Address(0),
+            ..Default::default()
+        };
+        let section_table = link(objects, &config).unwrap().section_table;
+
+        let text = section_table
+            .section_by_name(".text")
+            .unwrap()
+            .data
+            .bytes()
+            .unwrap();
+        assert_eq!(&text[0xa..0xe], &[0x33, 0x00, 0x00, 0x00]);
+        assert_eq!(&text[0xf..0x13], &[0x05, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn link_relocation_types() {
+        let objects = vec![LinkInput::Object(
+            read_relocatable_object("relocations.o", RELOCATIONS_O).unwrap(),
+        )];
+        let config = LinkConfig {
+            image_base: 0,
+            page_size: None,
+            entry: EntryPoint::Address(0),
+            ..Default::default()
+        };
+        let section_table = link(objects, &config).unwrap().section_table;
+
+        // `value` follows `table` at 0x1c
+        let data = section_table
+            .section_by_name(".data")
+            .unwrap()
+            .data
+            .bytes()
+            .unwrap();
+        assert_eq!(&data[0x0..0x8], &0x1c_u64.to_le_bytes());
+        assert_eq!(&data[0x8..0x10], &0x14_u64.to_le_bytes());
+        assert_eq!(&data[0x10..0x14], &12_u32.to_le_bytes());
+        assert_eq!(&data[0x14..0x1c], &12_u64.to_le_bytes());
+    }
+
+    #[test]
+    fn reject_truncated() {
+        assert_eq!(
+            read_relocatable_object("main.o", &MAIN_O[..0x20]).err(),
+            Some(ElfReadError::Truncated { what: "ELF header" })
+        );
+        assert_eq!(
+            read_relocatable_object("main.o", &MAIN_O[..0x100]).err(),
+            Some(ElfReadError::Truncated {
+                what: "section header"
+            })
+        );
+    }
+
+    #[test]
+    fn reject_non_elf() {
+        let mut bytes = MAIN_O.to_vec();
+        bytes[0] = 0;
+        assert_eq!(
+            read_relocatable_object("main.o", &bytes).err(),
+            Some(ElfReadError::InvalidMagic)
+        );
+    }
+}
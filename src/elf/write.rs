@@ -0,0 +1,883 @@
+use thiserror::Error;
+
+use crate::{
+    core::models::{Architecture, LoadableSectionTable},
+    in_memory::models::{Endianness, InMemoryExecutableObject, SectionKind},
+};
+
+use super::{dynamic, models::*};
+
+const PAGE_SIZE: u64 = 0x1000;
+
+/// Writes an ELF64 executable or shared object. An executable is static (`ET_EXEC`)
+/// or position-independent (`ET_DYN`); position-independent output gets the sections
+/// the loader reads, placed after the image: the exported symbols in `.dynsym`, with
+/// `.hash` and `.gnu.hash` to look them up; the dynamic relocations in `.rela.dyn`
+/// and `.rela.plt`; and a `.dynamic` section that points the loader to them. Its
+/// headers are loaded in front of the image, and an executable names its loader in
+/// `PT_INTERP`.
+///
+/// Consecutive output sections with the same permissions share a `PT_LOAD` segment;
+/// zero-fill sections only extend a segment's memory size.
+pub fn write_executable(executable: &InMemoryExecutableObject) -> Result<Vec<u8>, ElfWriteError> {
+    let section_table = &executable.section_table;
+    let dynamic_sections;
+    let mut sections = section_table
+        .indices()
+        .map(|index| {
+            let section = section_table.section(index);
+            OutputSection {
+                name: &section.name,
+                typ: match section.data.is_zero_fill() {
+                    true => SHT_NOBITS,
+                    false => SHT_PROGBITS,
+                },
+                flags: section_flags(section.kind),
+                address: section_table.address(index) as u64,
+                size: section_table.len(index) as u64,
+                bytes: section.data.bytes(),
+                alignment: section.alignment as u64,
+                entsize: 0,
+                link: 0,
+                info: 0,
+            }
+        })
+        .collect::<Vec<_>>();
+    let mut dynamic_section = None;
+    if executable.pie || executable.shared {
+        let end = sections
+            .iter()
+            .map(|section| section.address + section.size)
+            .max()
+            .unwrap_or(0);
+        // Section header indices start after the null section
+        let section_headers = section_table
+            .indices()
+            .enumerate()
+            .map(|(i, index)| (index, i as u32 + 1))
+            .collect();
+        dynamic_sections = dynamic::dynamic_sections(
+            executable,
+            end,
+            PAGE_SIZE,
+            &section_headers,
+            sections.len() as u32 + 1,
+        );
+        for section in &dynamic_sections {
+            if section.typ == SHT_DYNAMIC {
+                dynamic_section = Some(sections.len());
+            }
+            sections.push(OutputSection {
+                name: section.name,
+                typ: section.typ,
+                flags: section.flags,
+                address: section.address,
+                size: section.bytes.len() as u64,
+                bytes: Some(&section.bytes),
+                alignment: 8,
+                entsize: section.entsize,
+                link: section.link,
+                info: section.info,
+            });
+        }
+    }
+
+    // Group sections into segments by permission
+    let mut segments: Vec<Segment> = vec![];
+    for (i, section) in sections.iter().enumerate() {
+        let end = section.address + section.size;
+        let is_zero_fill = section.typ == SHT_NOBITS;
+        let file_end = match is_zero_fill {
+            true => section.address,
+            false => end,
+        };
+        let flags = segment_flags(section.flags);
+        match segments.last_mut() {
+            // Alignment padding is kept inside the segment; file contents must be a
+            // prefix of the segment
+            Some(last)
+                if last.flags == flags
+                    && (last.end..last.end + PAGE_SIZE).contains(&section.address)
+                    && (last.file_end == last.end || is_zero_fill) =>
+            {
+                last.end = end;
+                if !is_zero_fill {
+                    last.file_end = file_end;
+                }
+                last.sections.end = i + 1;
+            }
+            _ => segments.push(Segment {
+                flags,
+                start: section.address,
+                end,
+                file_end,
+                sections: i..i + 1,
+            }),
+        }
+    }
+    let loaded_segments = segments.iter().filter(|s| s.end > s.start).count();
+
+    // The loader of position-independent output reads the program headers from
+    // memory, so they are loaded on the page before the image, along with the path
+    // of the loader of an executable in `.interp`
+    let first_page = sections
+        .iter()
+        .map(|section| section.address)
+        .min()
+        .map_or(0, |address| address - address % PAGE_SIZE);
+    let headers_address = match executable.pie || executable.shared {
+        true => Some(
+            first_page
+                .checked_sub(PAGE_SIZE)
+                .ok_or(ElfWriteError::NoRoomForHeaders { first_page })?,
+        ),
+        false => None,
+    };
+    let interpreter = match (headers_address, executable.pie && !executable.shared) {
+        (Some(_), true) => Some(interpreter(executable.architecture, executable.endianness)),
+        _ => None,
+    };
+    let segment_count = loaded_segments
+        + dynamic_section.iter().count()
+        + headers_address.iter().count() * 2
+        + interpreter.iter().count();
+    let headers_size = (ElfHeader::SIZE + ElfProgramHeader::SIZE * segment_count) as u64;
+    let mut program_headers = vec![];
+    if let Some(address) = headers_address {
+        program_headers.push(ElfProgramHeader {
+            typ: PT_PHDR,
+            flags: PF_R,
+            offset: ElfHeader::SIZE as u64,
+            vaddr: address + ElfHeader::SIZE as u64,
+            paddr: address + ElfHeader::SIZE as u64,
+            filesz: headers_size - ElfHeader::SIZE as u64,
+            memsz: headers_size - ElfHeader::SIZE as u64,
+            align: 8,
+        });
+        if let Some(interpreter) = interpreter {
+            sections.push(OutputSection {
+                name: ".interp",
+                typ: SHT_PROGBITS,
+                flags: SHF_ALLOC,
+                address: address + headers_size,
+                size: interpreter.len() as u64,
+                bytes: Some(interpreter),
+                alignment: 1,
+                entsize: 0,
+                link: 0,
+                info: 0,
+            });
+            program_headers.push(ElfProgramHeader {
+                typ: PT_INTERP,
+                flags: PF_R,
+                offset: headers_size,
+                vaddr: address + headers_size,
+                paddr: address + headers_size,
+                filesz: interpreter.len() as u64,
+                memsz: interpreter.len() as u64,
+                align: 1,
+            });
+        }
+        let size = headers_size + interpreter.map_or(0, |interpreter| interpreter.len() as u64);
+        if size > PAGE_SIZE {
+            return Err(ElfWriteError::NoRoomForHeaders { first_page });
+        }
+        program_headers.push(ElfProgramHeader {
+            typ: PT_LOAD,
+            flags: PF_R,
+            offset: 0,
+            vaddr: address,
+            paddr: address,
+            filesz: size,
+            memsz: size,
+            align: PAGE_SIZE,
+        });
+    }
+
+    // Lay out section contents so that file offsets are congruent to addresses
+    let mut offset = match program_headers.last() {
+        Some(headers) => headers.filesz,
+        None => headers_size,
+    };
+    let mut section_offsets = vec![0; sections.len()];
+    if interpreter.is_some() {
+        section_offsets[sections.len() - 1] = headers_size;
+    }
+    for segment in &segments {
+        let segment_offset = align_congruent(offset, segment.start, PAGE_SIZE);
+        for i in segment.sections.clone() {
+            section_offsets[i] = segment_offset + sections[i].address - segment.start;
+        }
+        offset = segment_offset + segment.file_end - segment.start;
+        if segment.end == segment.start {
+            continue;
+        }
+        program_headers.push(ElfProgramHeader {
+            typ: PT_LOAD,
+            flags: segment.flags,
+            offset: segment_offset,
+            vaddr: segment.start,
+            paddr: segment.start,
+            filesz: segment.file_end - segment.start,
+            memsz: segment.end - segment.start,
+            align: PAGE_SIZE,
+        });
+    }
+    if let Some(i) = dynamic_section {
+        let section = &sections[i];
+        program_headers.push(ElfProgramHeader {
+            typ: PT_DYNAMIC,
+            flags: segment_flags(section.flags),
+            offset: section_offsets[i],
+            vaddr: section.address,
+            paddr: section.address,
+            filesz: section.size,
+            memsz: section.size,
+            align: section.alignment,
+        });
+    }
+
+    // Section names
+    let mut section_names = vec![0];
+    let mut name_offsets = vec![];
+    for section in sections.iter().map(|s| s.name).chain([".shstrtab"]) {
+        name_offsets.push(section_names.len() as u32);
+        section_names.extend_from_slice(section.as_bytes());
+        section_names.push(0);
+    }
+    let section_names_offset = offset;
+    offset += section_names.len() as u64;
+
+    // Section headers
+    let section_headers_offset = align_up(offset, 8);
+    let mut section_headers = vec![ElfSectionHeader {
+        name: 0,
+        typ: SHT_NULL,
+        flags: 0,
+        addr: 0,
+        offset: 0,
+        size: 0,
+        link: 0,
+        info: 0,
+        addralign: 0,
+        entsize: 0,
+    }];
+    for (i, section) in sections.iter().enumerate() {
+        section_headers.push(ElfSectionHeader {
+            name: name_offsets[i],
+            typ: section.typ,
+            flags: section.flags,
+            addr: section.address,
+            offset: section_offsets[i],
+            size: section.size,
+            link: section.link,
+            info: section.info,
+            addralign: section.alignment,
+            entsize: section.entsize,
+        });
+    }
+    section_headers.push(ElfSectionHeader {
+        name: name_offsets[sections.len()],
+        typ: SHT_STRTAB,
+        flags: 0,
+        addr: 0,
+        offset: section_names_offset,
+        size: section_names.len() as u64,
+        link: 0,
+        info: 0,
+        addralign: 1,
+        entsize: 0,
+    });
+
+    let mut ident = [0; 16];
+    ident[..4].copy_from_slice(&ELF_MAGIC);
+    ident[EI_CLASS] = ELFCLASS64;
+    ident[EI_DATA] = match executable.endianness {
+        Endianness::Little => ELFDATA2LSB,
+        Endianness::Big => ELFDATA2MSB,
+    };
+    ident[EI_VERSION] = EV_CURRENT;
+    let header = ElfHeader {
+        ident,
+        typ: match executable.pie || executable.shared {
+            true => ET_DYN,
+            false => ET_EXEC,
+        },
+        machine: match executable.architecture {
+            Architecture::X86_64 => EM_X86_64,
+            Architecture::Aarch64 => EM_AARCH64,
+            Architecture::RiscV => EM_RISCV,
+        },
+        version: EV_CURRENT as u32,
+        entry: executable.entry as u64,
+        phoff: ElfHeader::SIZE as u64,
+        shoff: section_headers_offset,
+        flags: 0,
+        ehsize: ElfHeader::SIZE as u16,
+        phentsize: ElfProgramHeader::SIZE as u16,
+        phnum: program_headers.len() as u16,
+        shentsize: ElfSectionHeader::SIZE as u16,
+        shnum: section_headers.len() as u16,
+        shstrndx: (section_headers.len() - 1) as u16,
+    };
+
+    // Emit the file
+    let mut out = vec![];
+    header.write(&mut out);
+    for program_header in &program_headers {
+        program_header.write(&mut out, executable.endianness);
+    }
+    for (i, section) in sections.iter().enumerate() {
+        if let Some(bytes) = section.bytes {
+            write_at(&mut out, section_offsets[i], bytes);
+        }
+    }
+    write_at(&mut out, section_names_offset, &section_names);
+    out.resize(section_headers_offset as usize, 0);
+    for section_header in &section_headers {
+        section_header.write(&mut out, executable.endianness);
+    }
+
+    Ok(out)
+}
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum ElfWriteError {
+    #[error("No free page below the image at {first_page:#x} for the headers")]
+    NoRoomForHeaders { first_page: u64 },
+}
+
+/// The loader of position-independent executables, with a terminating null.
+fn interpreter(architecture: Architecture, endianness: Endianness) -> &'static [u8] {
+    match (architecture, endianness) {
+        (Architecture::X86_64, _) => b"/lib64/ld-linux-x86-64.so.2\0",
+        (Architecture::Aarch64, Endianness::Little) => b"/lib/ld-linux-aarch64.so.1\0",
+        (Architecture::Aarch64, Endianness::Big) => b"/lib/ld-linux-aarch64_be.so.1\0",
+        // The soft-float ABI, as no floating-point ABI is recorded in `e_flags`
+        (Architecture::RiscV, _) => b"/lib/ld-linux-riscv64-lp64.so.1\0",
+    }
+}
+
+/// An output section as written, whether linked or synthesized for the loader.
+struct OutputSection<'a> {
+    name: &'a str,
+    typ: u32,
+    flags: u64,
+    address: u64,
+    size: u64,
+    /// The contents, unless the section is zero-fill.
+    bytes: Option<&'a [u8]>,
+    alignment: u64,
+    entsize: u64,
+    link: u32,
+    info: u32,
+}
+
+struct Segment {
+    flags: u32,
+    start: u64,
+    end: u64,
+    /// The end of the part backed by file contents.
+    file_end: u64,
+    /// Positions of the output sections in this segment.
+    sections: std::ops::Range<usize>,
+}
+
+fn section_flags(kind: SectionKind) -> u64 {
+    match kind {
+        SectionKind::Text => SHF_ALLOC | SHF_EXECINSTR,
+        SectionKind::ReadOnlyData => SHF_ALLOC,
+        SectionKind::Data => SHF_ALLOC | SHF_WRITE,
+    }
+}
+
+fn segment_flags(section_flags: u64) -> u32 {
+    let mut flags = PF_R;
+    if section_flags & SHF_WRITE != 0 {
+        flags |= PF_W;
+    }
+    if section_flags & SHF_EXECINSTR != 0 {
+        flags |= PF_X;
+    }
+    flags
+}
+
+fn align_up(value: u64, alignment: u64) -> u64 {
+    value.div_ceil(alignment) * alignment
+}
+
+/// Returns the smallest offset `>= offset` that is congruent to `address` modulo `alignment`.
+fn align_congruent(offset: u64, address: u64, alignment: u64) -> u64 {
+    let candidate = offset - offset % alignment + address % alignment;
+    if candidate >= offset {
+        candidate
+    } else {
+        candidate + alignment
+    }
+}
+
+fn write_at(out: &mut Vec<u8>, offset: u64, bytes: &[u8]) {
+    let offset = offset as usize;
+    if out.len() < offset + bytes.len() {
+        out.resize(offset + bytes.len(), 0);
+    }
+    out[offset..offset + bytes.len()].copy_from_slice(bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        core::models::{Relocation, RelocationType, X86_64RelocationType},
+        elf::read_relocatable_object,
+        in_memory::{
+            link,
+            models::{EntryPoint, LinkConfig},
+            LinkInput,
+        },
+    };
+
+    use std::collections::HashMap;
+
+    use super::*;
+
+    const MAIN_O: &[u8] = include_bytes!("../../fixtures/main.o");
+    const SUM_O: &[u8] = include_bytes!("../../fixtures/sum.o");
+    const COUNTER_O: &[u8] = include_bytes!("../../fixtures/counter.o");
+    const START_O: &[u8] = include_bytes!("../../fixtures/start.o");
+    const PIE_O: &[u8] = include_bytes!("../../fixtures/pie.o");
+    const CALL_SUM_O: &[u8] = include_bytes!("../../fixtures/call_sum.o");
+    const TEXT_RELOCATION_O: &[u8] = include_bytes!("../../fixtures/text_relocation.o");
+    const AARCH64_O: &[u8] = include_bytes!("../../fixtures/aarch64.o");
+    const AARCH64_BE_O: &[u8] = include_bytes!("../../fixtures/aarch64_be.o");
+
+    #[test]
+    fn write_main_sum() {
+        let objects = vec![
+            LinkInput::Object(read_relocatable_object("main.o", MAIN_O).unwrap()),
+            LinkInput::Object(read_relocatable_object("sum.o", SUM_O).unwrap()),
+        ];
+        let config = LinkConfig {
+            entry: EntryPoint::Symbol("sum".to_string()),
+            ..Default::default()
+        };
+        let executable = link(objects, &config).unwrap();
+        let bytes = write_executable(&executable).unwrap();
+
+        let header = ElfHeader::parse(&bytes).unwrap();
+        assert_eq!(header.typ, ET_EXEC);
+        assert_eq!(header.entry, 0x400018);
+        assert_eq!(header.phnum, 2);
+        assert_eq!(header.shnum, 5);
+
+        let text = ElfProgramHeader::parse(&bytes[ElfHeader::SIZE..], Endianness::Little).unwrap();
+        assert_eq!(text.flags, PF_R | PF_X);
+        assert_eq!(text.vaddr, 0x400000);
+        assert_eq!(text.filesz, 0x33);
+        assert_eq!(text.offset % PAGE_SIZE, text.vaddr % PAGE_SIZE);
+        let data = ElfProgramHeader::parse(
+            &bytes[ElfHeader::SIZE + ElfProgramHeader::SIZE..],
+            Endianness::Little,
+        )
+        .unwrap();
+        assert_eq!(data.flags, PF_R | PF_W);
+        assert_eq!(data.vaddr, 0x401033);
+        assert_eq!(data.filesz, 8);
+        assert_eq!(data.offset % PAGE_SIZE, data.vaddr % PAGE_SIZE);
+
+        let text_bytes = &bytes[text.offset as usize..(text.offset + text.filesz) as usize];
+        assert_eq!(
+            text_bytes,
+            executable
+                .section_table
+                .section_by_name(".text")
+                .unwrap()
+                .data
+                .bytes()
+                .unwrap()
+        );
+        let data_bytes = &bytes[data.offset as usize..(data.offset + data.filesz) as usize];
+        assert_eq!(data_bytes, &[1, 0, 0, 0, 2, 0, 0, 0]);
+    }
+
+    #[test]
+    fn zero_fill_is_memory_only() {
+        let objects = vec![
+            LinkInput::Object(read_relocatable_object("main.o", MAIN_O).unwrap()),
+            LinkInput::Object(read_relocatable_object("sum.o", SUM_O).unwrap()),
+            LinkInput::Object(read_relocatable_object("counter.o", COUNTER_O).unwrap()),
+        ];
+        let config = LinkConfig {
+            entry: EntryPoint::Symbol("main".to_string()),
+            ..Default::default()
+        };
+        let executable = link(objects, &config).unwrap();
+        let bytes = write_executable(&executable).unwrap();
+
+        let header = ElfHeader::parse(&bytes).unwrap();
+        let data = ElfProgramHeader::parse(
+            &bytes[ElfHeader::SIZE + ElfProgramHeader::SIZE..],
+            Endianness::Little,
+        )
+        .unwrap();
+        assert_eq!(data.vaddr, 0x40103c);
+        assert_eq!(data.filesz, 8);
+        assert_eq!(data.memsz, 8 + 16);
+
+        let bss_offset = header.shoff as usize + 3 * ElfSectionHeader::SIZE;
+        let bss = ElfSectionHeader::parse(&bytes[bss_offset..], Endianness::Little).unwrap();
+        assert_eq!(bss.typ, SHT_NOBITS);
+        assert_eq!(bss.addr, 0x401044);
+        assert_eq!(bss.size, 16);
+    }
+
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    #[test]
+    fn execute_pie() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // The loader must apply the relative relocation of the pointer to `array`
+        let objects = vec![
+            LinkInput::Object(read_relocatable_object("pie.o", PIE_O).unwrap()),
+            LinkInput::Object(read_relocatable_object("sum.o", SUM_O).unwrap()),
+        ];
+        let config = LinkConfig {
+            pie: true,
+            ..Default::default()
+        };
+        let executable = link(objects, &config).unwrap();
+        let bytes = write_executable(&executable).unwrap();
+
+        let path = std::env::temp_dir().join(format!("linker-execute-pie-{}", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let status = std::process::Command::new(&path).status().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // `_start` exits with the sum of `array`
+        assert_eq!(status.code(), Some(3));
+    }
+
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    #[test]
+    fn execute_pie_with_text_relocation() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // The loader must patch the absolute address of `array` in `.text`
+        let objects = vec![
+            LinkInput::Object(
+                read_relocatable_object("text_relocation.o", TEXT_RELOCATION_O).unwrap(),
+            ),
+            LinkInput::Object(read_relocatable_object("sum.o", SUM_O).unwrap()),
+        ];
+        let config = LinkConfig {
+            pie: true,
+            ..Default::default()
+        };
+        let executable = link(objects, &config).unwrap();
+        assert_eq!(executable.diagnostics.len(), 1);
+        let bytes = write_executable(&executable).unwrap();
+
+        let path = std::env::temp_dir().join(format!(
+            "linker-execute-text-relocation-{}",
+            std::process::id()
+        ));
+        std::fs::write(&path, bytes).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let status = std::process::Command::new(&path).status().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // `_start` exits with the sum of `array`
+        assert_eq!(status.code(), Some(3));
+    }
+
+    #[test]
+    fn position_independent_executable() {
+        // `sum.o` with a pointer to `sum` in `.data`
+        let mut sum = read_relocatable_object("sum.o", SUM_O).unwrap();
+        let data = sum
+            .section_table
+            .add_section(".data", SectionKind::Data, 8, vec![0; 8]);
+        let (symbol, _) = sum
+            .symbol_table
+            .iter()
+            .find(|(_, symbol)| symbol.name == "sum")
+            .unwrap();
+        sum.references.push(Relocation {
+            offset: 0,
+            section: data,
+            typ: RelocationType::X86_64(X86_64RelocationType::Direct64),
+            symbol,
+            addend: 0,
+        });
+        let config = LinkConfig {
+            image_base: 0,
+            entry: EntryPoint::Symbol("sum".to_string()),
+            pie: true,
+            ..Default::default()
+        };
+        let executable = link(vec![LinkInput::Object(sum)], &config).unwrap();
+        let bytes = write_executable(&executable).unwrap();
+
+        let header = ElfHeader::parse(&bytes).unwrap();
+        assert_eq!(header.typ, ET_DYN);
+        assert_eq!(header.phnum, 8);
+
+        // The headers and the path of the loader are loaded on the page before the
+        // image
+        let program_headers = (0..header.phnum as usize)
+            .map(|i| {
+                ElfProgramHeader::parse(
+                    &bytes[ElfHeader::SIZE + i * ElfProgramHeader::SIZE..],
+                    Endianness::Little,
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let types = program_headers.iter().map(|ph| ph.typ).collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            [PT_PHDR, PT_INTERP, PT_LOAD, PT_LOAD, PT_LOAD, PT_LOAD, PT_LOAD, PT_DYNAMIC]
+        );
+        assert_eq!(program_headers[0].vaddr, ElfHeader::SIZE as u64);
+        assert_eq!(
+            (program_headers[2].offset, program_headers[2].vaddr),
+            (0, 0)
+        );
+        let interp = &program_headers[1];
+        assert_eq!(
+            &bytes[interp.offset as usize..(interp.offset + interp.filesz) as usize],
+            interpreter(Architecture::X86_64, Endianness::Little)
+        );
+        assert_eq!(program_headers[3].vaddr, 0x1000);
+
+        // `.dynamic` follows the other dynamic sections on a page of its own
+        let dynamic = &program_headers[7];
+        assert_eq!(dynamic.vaddr, 0x4000);
+        assert_eq!(program_headers[6].offset, dynamic.offset);
+        let entries = (0..dynamic.filesz as usize / ElfDynamic::SIZE)
+            .map(|i| {
+                ElfDynamic::parse(
+                    &bytes[dynamic.offset as usize + i * ElfDynamic::SIZE..],
+                    Endianness::Little,
+                )
+                .unwrap()
+            })
+            .map(|entry| (entry.tag, entry.value))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(entries[&DT_RELASZ], ElfRela::SIZE as u64);
+        assert_eq!(entries[&DT_RELACOUNT], 1);
+        assert_eq!(entries[&DT_FLAGS_1], DF_1_PIE);
+
+        // The relocation holds the link-time address of `sum`
+        let relocations = &program_headers[5];
+        assert_eq!(relocations.flags, PF_R);
+        let offset = relocations.offset + entries[&DT_RELA] - relocations.vaddr;
+        let relocation = ElfRela::parse(&bytes[offset as usize..], Endianness::Little).unwrap();
+        assert_eq!(relocation.typ(), R_X86_64_RELATIVE);
+        assert_eq!(relocation.offset, 0x2020);
+        assert_eq!(relocation.addend, 0x1000);
+    }
+
+    #[test]
+    fn no_room_for_headers() {
+        // `.text` on the first page leaves no page below it for the headers
+        let sum = read_relocatable_object("sum.o", SUM_O).unwrap();
+        let config = LinkConfig {
+            image_base: 0,
+            entry: EntryPoint::Symbol("sum".to_string()),
+            pie: true,
+            section_starts: HashMap::from([(".text".to_string(), 0x10)]),
+            ..Default::default()
+        };
+        let executable = link(vec![LinkInput::Object(sum)], &config).unwrap();
+        assert_eq!(
+            write_executable(&executable).err(),
+            Some(ElfWriteError::NoRoomForHeaders { first_page: 0 })
+        );
+    }
+
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    #[test]
+    fn execute() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let objects = vec![
+            LinkInput::Object(read_relocatable_object("start.o", START_O).unwrap()),
+            LinkInput::Object(read_relocatable_object("main.o", MAIN_O).unwrap()),
+            LinkInput::Object(read_relocatable_object("sum.o", SUM_O).unwrap()),
+        ];
+        let executable = link(objects, &LinkConfig::default()).unwrap();
+        let bytes = write_executable(&executable).unwrap();
+
+        let path = std::env::temp_dir().join(format!("linker-execute-{}", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let status = std::process::Command::new(&path).status().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // `main` returns the sum of `array`
+        assert_eq!(status.code(), Some(3));
+    }
+
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    #[test]
+    fn dlopen_shared_object() {
+        use std::ffi::{c_char, c_int, c_void, CStr, CString};
+
+        extern "C" {
+            fn dlopen(filename: *const c_char, flags: c_int) -> *mut c_void;
+            fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+            fn dlerror() -> *const c_char;
+            fn dlclose(handle: *mut c_void) -> c_int;
+        }
+        const RTLD_LAZY: c_int = 1;
+
+        let objects = vec![
+            LinkInput::Object(read_relocatable_object("call_sum.o", CALL_SUM_O).unwrap()),
+            LinkInput::Object(read_relocatable_object("sum.o", SUM_O).unwrap()),
+        ];
+        let config = LinkConfig {
+            image_base: 0,
+            shared: true,
+            soname: Some("libcall_sum.so".to_string()),
+            ..Default::default()
+        };
+        let executable = link(objects, &config).unwrap();
+        let bytes = write_executable(&executable).unwrap();
+
+        let path = std::env::temp_dir().join(format!("linker-dlopen-{}.so", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        let c_path = CString::new(path.to_str().unwrap()).unwrap();
+        let (call_sum, sum, missing) = unsafe {
+            let handle = dlopen(c_path.as_ptr(), RTLD_LAZY);
+            std::fs::remove_file(&path).unwrap();
+            assert!(
+                !handle.is_null(),
+                "{}",
+                CStr::from_ptr(dlerror()).to_string_lossy()
+            );
+
+            // Symbols are looked up through `.gnu.hash`
+            let call_sum = dlsym(handle, c"call_sum".as_ptr());
+            let sum = dlsym(handle, c"sum".as_ptr());
+            let missing = dlsym(handle, c"missing".as_ptr());
+            assert!(!call_sum.is_null() && !sum.is_null());
+            let call_sum: extern "C" fn() -> c_int = std::mem::transmute(call_sum);
+            let sum: extern "C" fn(*const c_int, c_int) -> c_int = std::mem::transmute(sum);
+
+            // `call_sum` calls `sum` through the PLT, binding it lazily, with the
+            // address of `array` from the GOT
+            let values = (call_sum(), sum([4, 5, 6].as_ptr(), 3), missing.is_null());
+            dlclose(handle);
+            values
+        };
+        assert_eq!((call_sum, sum, missing), (3, 15, true));
+    }
+
+    #[test]
+    fn machine_and_byte_order() {
+        let write_with = |bytes, endianness| {
+            let objects = vec![LinkInput::Object(
+                read_relocatable_object("aarch64.o", bytes).unwrap(),
+            )];
+            let config = LinkConfig {
+                architecture: Architecture::Aarch64,
+                endianness,
+                ..Default::default()
+            };
+            write_executable(&link(objects, &config).unwrap()).unwrap()
+        };
+
+        let bytes = write_with(AARCH64_O, Endianness::Little);
+        let header = ElfHeader::parse(&bytes).unwrap();
+        assert_eq!(header.machine, EM_AARCH64);
+        assert_eq!(header.ident[EI_DATA], ELFDATA2LSB);
+        assert_eq!(&bytes[18..20], &EM_AARCH64.to_le_bytes());
+
+        // Every header field of big-endian output is big-endian
+        let bytes = write_with(AARCH64_BE_O, Endianness::Big);
+        let header = ElfHeader::parse(&bytes).unwrap();
+        assert_eq!(header.machine, EM_AARCH64);
+        assert_eq!(header.ident[EI_DATA], ELFDATA2MSB);
+        assert_eq!(&bytes[18..20], &EM_AARCH64.to_be_bytes());
+        let text = ElfProgramHeader::parse(&bytes[ElfHeader::SIZE..], Endianness::Big).unwrap();
+        assert_eq!((text.typ, text.vaddr), (PT_LOAD, 0x400000));
+        let data = ElfProgramHeader::parse(
+            &bytes[ElfHeader::SIZE + ElfProgramHeader::SIZE..],
+            Endianness::Big,
+        )
+        .unwrap();
+        assert_eq!(
+            &bytes[data.offset as usize..(data.offset + data.filesz) as usize],
+            &0x400024_u64.to_be_bytes()
+        );
+    }
+
+    #[test]
+    fn shared_object() {
+        let sum = read_relocatable_object("sum.o", SUM_O).unwrap();
+        let config = LinkConfig {
+            image_base: 0,
+            shared: true,
+            soname: Some("libsum.so".to_string()),
+            ..Default::default()
+        };
+        let executable = link(vec![LinkInput::Object(sum)], &config).unwrap();
+        let bytes = write_executable(&executable).unwrap();
+
+        let header = ElfHeader::parse(&bytes).unwrap();
+        assert_eq!(header.typ, ET_DYN);
+        let section_headers = (0..header.shnum as usize)
+            .map(|i| {
+                let offset = header.shoff as usize + i * ElfSectionHeader::SIZE;
+                ElfSectionHeader::parse(&bytes[offset..], Endianness::Little).unwrap()
+            })
+            .collect::<Vec<_>>();
+        let section = |typ| {
+            section_headers
+                .iter()
+                .find(|section| section.typ == typ)
+                .unwrap()
+        };
+        let contents = |section: &ElfSectionHeader| {
+            &bytes[section.offset as usize..(section.offset + section.size) as usize]
+        };
+        let dynsym = section(SHT_DYNSYM);
+        let dynstr = contents(&section_headers[dynsym.link as usize]);
+        let string = |offset: u64| {
+            let string = &dynstr[offset as usize..];
+            std::str::from_utf8(&string[..string.iter().position(|&b| b == 0).unwrap()]).unwrap()
+        };
+
+        // `sum` is exported as a function in `.text`
+        assert_eq!(dynsym.size, 2 * ElfSymbol::SIZE as u64);
+        let symbol =
+            ElfSymbol::parse(&contents(dynsym)[ElfSymbol::SIZE..], Endianness::Little).unwrap();
+        assert_eq!(string(symbol.name as u64), "sum");
+        assert_eq!(symbol.info, STB_GLOBAL << 4 | STT_FUNC);
+        let text = &section_headers[symbol.shndx as usize];
+        assert_eq!(text.flags, SHF_ALLOC | SHF_EXECINSTR);
+        assert_eq!(symbol.value, text.addr);
+        // Loaders take a symbol at address 0 to be undefined
+        assert_ne!(symbol.value, 0);
+
+        // `.hash` finds it
+        let hash = contents(section(SHT_HASH));
+        let word = |i: usize| u32::from_le_bytes(hash[i * 4..i * 4 + 4].try_into().unwrap());
+        let hash_of_sum = ((b's' as u32) << 8) + ((b'u' as u32) << 4) + b'm' as u32;
+        assert_eq!(word(2 + (hash_of_sum % word(0)) as usize), 1);
+
+        // `.dynamic` names the object
+        let dynamic = contents(section(SHT_DYNAMIC));
+        let entries = (0..dynamic.len() / ElfDynamic::SIZE)
+            .map(|i| {
+                ElfDynamic::parse(&dynamic[i * ElfDynamic::SIZE..], Endianness::Little).unwrap()
+            })
+            .map(|entry| (entry.tag, entry.value))
+            .collect::<Vec<_>>();
+        let soname = entries.iter().find(|(tag, _)| *tag == DT_SONAME).unwrap();
+        assert_eq!(string(soname.1), "libsum.so");
+        assert!(entries.contains(&(DT_SYMTAB, dynsym.addr)));
+        assert!(!entries.iter().any(|(tag, _)| *tag == DT_FLAGS_1));
+    }
+}
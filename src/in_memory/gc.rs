@@ -0,0 +1,263 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::core::models::{Relocation, Symbol, SymbolBinding, SymbolDefinition, SymbolValue};
+
+use super::{
+    models::{
+        EntryPoint, InMemoryRelocatableObject, InMemorySectionIndex, LinkConfig, RemovedSection,
+    },
+    unwind::{remove_unwind_records, unwind_records, UnwindRecord, PC_BEGIN},
+};
+
+/// An input section, by the position of its object.
+pub type InputSection = (usize, InMemorySectionIndex);
+
+/// A symbol definition, by its input section and offset.
+pub type Definition = (InputSection, usize);
+
+/// Discards the input sections that cannot be reached through relocations from the
+/// entry symbol, the exported symbols or sections marked to keep. Unwind tables are
+/// kept, but lose the FDEs of discarded functions.
+///
+/// Returns the discarded sections with contents.
+pub fn collect_garbage(
+    objects: &mut [InMemoryRelocatableObject],
+    config: &LinkConfig,
+) -> Vec<RemovedSection> {
+    let definitions = definitions(objects);
+
+    // Find the sections each section refers to. An FDE describes the function it
+    // refers to rather than uses it, so the rest of what it refers to (e.g. the LSDA)
+    // is only needed along with the function
+    let mut unwind_tables = HashMap::new();
+    let mut edges: HashMap<InputSection, Vec<InputSection>> = HashMap::new();
+    for (position, object) in objects.iter().enumerate() {
+        for (index, section) in object.section_table.iter() {
+            if !section.unwind {
+                continue;
+            }
+            let Some(records) = section
+                .data
+                .bytes()
+                .and_then(|data| unwind_records(data, config.endianness))
+            else {
+                continue;
+            };
+            let functions = records
+                .iter()
+                .map(|record| {
+                    record.cie?;
+                    let reference = object.references.iter().find(|reference| {
+                        reference.section == index && reference.offset == record.offset + PC_BEGIN
+                    })?;
+                    target(&definitions, position, object, reference)
+                })
+                .collect();
+            unwind_tables.insert((position, index), UnwindTable { records, functions });
+        }
+        for reference in &object.references {
+            let Some(target) = target(&definitions, position, object, reference) else {
+                continue;
+            };
+            let fde = unwind_tables
+                .get(&(position, reference.section))
+                .and_then(|table: &UnwindTable| table.fde(reference.offset));
+            let source = match fde {
+                Some((record, _)) if reference.offset == record.offset + PC_BEGIN => continue,
+                Some((_, function)) => function,
+                None => (position, reference.section),
+            };
+            edges.entry(source).or_default().push(target);
+        }
+    }
+
+    // Mark the sections reachable from the roots
+    let entry = match &config.entry {
+        EntryPoint::Symbol(symbol) => Some(symbol),
+        EntryPoint::Address(_) => None,
+    };
+    let mut pending = entry
+        .into_iter()
+        .chain(&config.exported_symbols)
+        .filter_map(|symbol| definitions.get(symbol.as_str()).copied().flatten())
+        .map(|(section, _)| section)
+        .collect::<Vec<_>>();
+    // A shared object exports every global definition
+    if config.shared {
+        pending.extend(definitions.values().flatten().map(|(section, _)| *section));
+    }
+    for (position, object) in objects.iter().enumerate() {
+        pending.extend(
+            object
+                .section_table
+                .iter()
+                .filter(|(_, section)| section.keep || section.unwind)
+                .map(|(index, _)| (position, index)),
+        );
+    }
+    let mut live = HashSet::new();
+    while let Some(section) = pending.pop() {
+        if live.insert(section) {
+            pending.extend(edges.get(&section).into_iter().flatten());
+        }
+    }
+
+    // Sweep the others
+    let mut removed = vec![];
+    for (position, object) in objects.iter_mut().enumerate() {
+        removed.extend(
+            object
+                .section_table
+                .iter()
+                .filter(|(index, section)| {
+                    !live.contains(&(position, *index)) && !section.data.is_empty()
+                })
+                .map(|(_, section)| RemovedSection {
+                    object: object.name.to_string(),
+                    section: section.name.clone(),
+                }),
+        );
+        for (index, table) in unwind_tables.iter().filter(|((p, _), _)| *p == position) {
+            let (_, index) = *index;
+            if live.contains(&(position, index)) {
+                remove_dead_fdes(object, index, table, &live, config);
+            }
+        }
+        object.retain_sections(|index| live.contains(&(position, index)));
+    }
+    removed
+}
+
+/// The records of an unwind table, and the function that each FDE describes.
+struct UnwindTable {
+    records: Vec<UnwindRecord>,
+    functions: Vec<Option<InputSection>>,
+}
+impl UnwindTable {
+    /// The FDE that contains `offset`, and the function it describes.
+    fn fde(&self, offset: usize) -> Option<(UnwindRecord, InputSection)> {
+        let index = self
+            .records
+            .partition_point(|record| record.offset <= offset)
+            .checked_sub(1)?;
+        let record = self.records[index];
+        Some((record, self.functions[index]?)).filter(|_| record.contains(offset))
+    }
+}
+
+/// Removes the FDEs of removed functions from an unwind table, along with their
+/// relocations, and moves the relocations and symbols of the remaining records.
+fn remove_dead_fdes(
+    object: &mut InMemoryRelocatableObject,
+    index: InMemorySectionIndex,
+    table: &UnwindTable,
+    live: &HashSet<InputSection>,
+    config: &LinkConfig,
+) {
+    let dead = |record: usize| table.functions[record].is_some_and(|f| !live.contains(&f));
+    if !(0..table.records.len()).any(dead) {
+        return;
+    }
+    let data = object
+        .section_table
+        .section_mut(index)
+        .data
+        .bytes_mut()
+        .unwrap();
+    let kept = remove_unwind_records(data, &table.records, config.endianness, |record| {
+        !dead(record)
+    });
+    let new_offset = |offset: usize| {
+        kept.iter()
+            .find(|(record, _)| record.contains(offset))
+            .map(|(record, new_offset)| offset - record.offset + new_offset)
+    };
+
+    object.references = std::mem::take(&mut object.references)
+        .into_iter()
+        .filter_map(|reference| {
+            if reference.section != index {
+                return Some(reference);
+            }
+            Some(Relocation {
+                offset: new_offset(reference.offset)?,
+                ..reference
+            })
+        })
+        .collect();
+    let symbols = object
+        .symbol_table
+        .iter()
+        .filter_map(|(symbol_index, symbol)| match symbol.value {
+            SymbolValue::Defined(definition) if definition.section == index => {
+                let offset = new_offset(definition.offset)?;
+                let value = SymbolValue::Defined(SymbolDefinition {
+                    offset,
+                    ..definition
+                });
+                Some((symbol_index, Symbol { value, ..*symbol }))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    for (symbol_index, symbol) in symbols {
+        object.symbol_table.replace(symbol_index, symbol);
+    }
+}
+
+/// The section that a reference from the object at `position` refers to, if defined.
+fn target(
+    definitions: &HashMap<&str, Option<Definition>>,
+    position: usize,
+    object: &InMemoryRelocatableObject,
+    reference: &Relocation<InMemorySectionIndex>,
+) -> Option<InputSection> {
+    let symbol = object.symbol_table.get(reference.symbol);
+    match (symbol.binding, symbol.value) {
+        (SymbolBinding::Local, SymbolValue::Defined(definition)) => {
+            Some((position, definition.section))
+        }
+        (SymbolBinding::Local, _) => None,
+        _ => definitions
+            .get(symbol.name)
+            .copied()
+            .flatten()
+            .map(|(section, _)| section),
+    }
+}
+
+/// The section and offset of the definition that resolution will pick for each
+/// global symbol, or `None` for a common or absolute symbol.
+pub fn definitions<'name>(
+    objects: &[InMemoryRelocatableObject<'name>],
+) -> HashMap<&'name str, Option<Definition>> {
+    // A global definition overrides a tentative one, which overrides a weak one; the
+    // first of equals wins
+    let mut definitions: HashMap<&'name str, (u8, Option<Definition>)> = HashMap::new();
+    for (position, object) in objects.iter().enumerate() {
+        for (_, symbol) in object.symbol_table.iter() {
+            let (rank, section) = match (symbol.binding, symbol.value) {
+                (SymbolBinding::Local, _) | (_, SymbolValue::Undefined) => continue,
+                (SymbolBinding::Global, SymbolValue::Defined(definition)) => {
+                    (2, Some(((position, definition.section), definition.offset)))
+                }
+                (SymbolBinding::Global, SymbolValue::Absolute(_)) => (2, None),
+                (_, SymbolValue::Common { .. }) => (1, None),
+                (SymbolBinding::Weak, SymbolValue::Absolute(_)) => (0, None),
+                (SymbolBinding::Weak, SymbolValue::Defined(definition)) => {
+                    (0, Some(((position, definition.section), definition.offset)))
+                }
+            };
+            match definitions.get(symbol.name) {
+                Some(&(existing_rank, _)) if existing_rank >= rank => (),
+                _ => {
+                    definitions.insert(symbol.name, (rank, section));
+                }
+            }
+        }
+    }
+    definitions
+        .into_iter()
+        .map(|(name, (_, section))| (name, section))
+        .collect()
+}
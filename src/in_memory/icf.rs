@@ -0,0 +1,176 @@
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    hash::Hash,
+};
+
+use crate::core::models::{RelocationType, SymbolBinding, SymbolIndex, SymbolValue};
+
+use super::{
+    gc::{definitions, Definition, InputSection},
+    models::{output_section_name, Icf, InMemoryRelocatableObject, SectionKind},
+};
+
+/// What a relocation refers to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum Target<'name> {
+    /// An offset within an input section.
+    Section(InputSection, usize),
+    /// A symbol without a definition in a section.
+    Symbol(&'name str),
+    /// The value of a local absolute symbol.
+    Absolute(usize),
+}
+
+/// Folds read-only input sections with identical contents and equivalent relocation
+/// targets into the first of them.
+///
+/// Returns the section each folded section was folded into.
+pub fn fold_identical_sections<'name>(
+    objects: &mut [InMemoryRelocatableObject<'name>],
+    icf: Icf,
+) -> HashMap<InputSection, InputSection> {
+    if icf == Icf::None {
+        return HashMap::new();
+    }
+    let definitions = definitions(objects);
+
+    // Collect the relocations of each section, and the sections whose address is taken
+    let mut relocations: HashMap<InputSection, Vec<(usize, RelocationType, isize, Target<'name>)>> =
+        HashMap::new();
+    let mut address_taken = HashSet::new();
+    for (position, object) in objects.iter().enumerate() {
+        for reference in &object.references {
+            let target = target(&definitions, position, object, reference.symbol);
+            if let (false, Target::Section(section, _)) = (reference.typ.is_branch(), target) {
+                address_taken.insert(section);
+            }
+            relocations
+                .entry((position, reference.section))
+                .or_default()
+                .push((reference.offset, reference.typ, reference.addend, target));
+        }
+    }
+    for relocations in relocations.values_mut() {
+        relocations.sort_by_key(|&(offset, ..)| offset);
+    }
+
+    // Writable sections, unwind tables and sections that must stay distinct are never
+    // folded
+    let candidates = objects
+        .iter()
+        .enumerate()
+        .flat_map(|(position, object)| {
+            object
+                .section_table
+                .iter()
+                .filter(|(_, section)| {
+                    section.kind != SectionKind::Data
+                        && !section.keep
+                        && !section.unwind
+                        && !section.data.is_empty()
+                        && !section.data.is_zero_fill()
+                })
+                .map(move |(index, _)| (position, index))
+        })
+        .filter(|section| icf == Icf::All || !address_taken.contains(section))
+        .collect::<Vec<_>>();
+
+    // Partition by output section and contents, then split classes whose relocations refer to different
+    // classes until no class splits
+    let (mut classes, mut count) = partition(&candidates, |&(position, index)| {
+        let section = objects[position].section_table.section(index);
+        let relocations = relocations
+            .get(&(position, index))
+            .into_iter()
+            .flatten()
+            .map(|&(offset, typ, addend, _)| (offset, typ, addend))
+            .collect::<Vec<_>>();
+        (
+            output_section_name(&section.name),
+            section.kind,
+            section.alignment,
+            section.data.bytes(),
+            relocations,
+        )
+    });
+    loop {
+        let (refined_classes, refined_count) = partition(&candidates, |section| {
+            let targets = relocations
+                .get(section)
+                .into_iter()
+                .flatten()
+                .map(|&(_, _, _, target)| match target {
+                    Target::Section(section, offset) => match classes.get(&section) {
+                        Some(&class) => Ok((class, offset)),
+                        None => Err(target),
+                    },
+                    Target::Symbol(_) | Target::Absolute(_) => Err(target),
+                })
+                .collect::<Vec<_>>();
+            (classes[section], targets)
+        });
+        classes = refined_classes;
+        if refined_count == count {
+            break;
+        }
+        count = refined_count;
+    }
+
+    // Fold every section into the first of its class
+    let mut representatives = HashMap::new();
+    let mut folds = HashMap::new();
+    for section in candidates {
+        match representatives.entry(classes[&section]) {
+            Entry::Occupied(entry) => {
+                folds.insert(section, *entry.get());
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(section);
+            }
+        }
+    }
+    for &(position, index) in folds.keys() {
+        objects[position].fold_section(index);
+    }
+    folds
+}
+
+/// Where the symbol of a relocation in the object at `position` resolves to.
+fn target<'name>(
+    definitions: &HashMap<&'name str, Option<Definition>>,
+    position: usize,
+    object: &InMemoryRelocatableObject<'name>,
+    symbol: SymbolIndex,
+) -> Target<'name> {
+    let symbol = object.symbol_table.get(symbol);
+    match (symbol.binding, symbol.value) {
+        (SymbolBinding::Local, SymbolValue::Defined(definition)) => {
+            Target::Section((position, definition.section), definition.offset)
+        }
+        (SymbolBinding::Local, SymbolValue::Absolute(value)) => Target::Absolute(value),
+        (SymbolBinding::Local, _) => Target::Symbol(symbol.name),
+        _ => match definitions.get(symbol.name) {
+            Some(&Some((section, offset))) => Target::Section(section, offset),
+            _ => Target::Symbol(symbol.name),
+        },
+    }
+}
+
+/// Numbers the sections by their key, and returns the number of distinct keys.
+fn partition<K>(
+    sections: &[InputSection],
+    key: impl Fn(&InputSection) -> K,
+) -> (HashMap<InputSection, usize>, usize)
+where
+    K: Hash + Eq,
+{
+    let mut ids = HashMap::new();
+    let classes = sections
+        .iter()
+        .map(|section| {
+            let id = ids.len();
+            (*section, *ids.entry(key(section)).or_insert(id))
+        })
+        .collect();
+    (classes, ids.len())
+}
@@ -1,49 +1,753 @@
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fmt,
+    mem::{self, Discriminant},
+};
+
 use thiserror::Error;
 
 use crate::core::{
-    models::ResolvingSymbolTable, relocate_reference, resolve_unloadable_sections, RelocationError,
-    ResolveError,
+    models::{
+        Architecture, FieldLayout, LoadableSectionTable, RelocationFormula, RelocationOrigin,
+        ResolvingRelocation, ResolvingSymbolTable, SectionMapping, SectionPlacement, Symbol,
+        SymbolBinding, SymbolDefinition, SymbolValue,
+    },
+    relocate_reference, resolve_unloadable_sections, GlobalOffsetTable, GotValue,
+    HighPartRelocations, RelocationError, ResolveError,
 };
 
-use super::models::{
-    InMemoryLoadableSectionTable, InMemoryRelocatableObject, InMemorySectionIndex,
+use super::{
+    gc::{collect_garbage, InputSection},
+    icf::fold_identical_sections,
+    models::{
+        DynamicRelocation, DynamicRelocationKind, Endianness, EntryPoint, InMemoryArchive,
+        InMemoryExecutableObject, InMemoryLoadableSectionTable, InMemoryRelocatableObject,
+        InMemorySectionIndex, LayoutError, LinkConfig, SectionKind, ValidationError,
+    },
+    plt::ProcedureLinkageTable,
 };
 
-pub fn link(
-    objects: Vec<InMemoryRelocatableObject>,
-    address_len: usize,
-) -> Result<InMemoryLoadableSectionTable, LinkError> {
+/// An input to `link`, in command-line order.
+pub enum LinkInput<'name> {
+    Object(InMemoryRelocatableObject<'name>),
+    /// Members are linked when they define a symbol undefined at this point.
+    Archive(InMemoryArchive<'name>),
+    /// Archives searched repeatedly until none of them defines an undefined
+    /// symbol, like `--start-group` and `--end-group`.
+    Group(Vec<InMemoryArchive<'name>>),
+}
+
+/// Links the inputs into an executable.
+///
+/// Resolution and relocation continue past failures, so that every problem is
+/// reported at once; the diagnostics are grouped by symbol. A successful link
+/// returns its warnings in the output.
+pub fn link<'name>(
+    inputs: Vec<LinkInput<'name>>,
+    config: &LinkConfig,
+) -> Result<InMemoryExecutableObject<'name>, Vec<Diagnostic>> {
     let mut section_table = InMemoryLoadableSectionTable::new();
     let mut symbol_table = ResolvingSymbolTable::new();
     let mut references = Vec::new();
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+
+    // Select the objects to link, then drop what they do not need
+    let mut objects = select_objects(inputs, config.architecture, &mut errors);
+    let removed_sections = match config.gc_sections {
+        true => collect_garbage(&mut objects, config),
+        false => vec![],
+    };
+
+    let mut folded_sections = FoldedSections {
+        folds: fold_identical_sections(&mut objects, config.icf),
+        placements: HashMap::new(),
+    };
 
     // Resolve objects
-    for object in objects.into_iter() {
-        // Resolve unloadable sections
-        resolve_unloadable_sections(
+    for (position, object) in objects.into_iter().enumerate() {
+        resolve_object(
+            position,
+            object,
+            &mut folded_sections,
             &mut section_table,
             &mut symbol_table,
-            object.symbol_table,
             &mut references,
-            object.references,
-        )?;
+            &mut errors,
+        );
+    }
+
+    // Allocate tentative definitions and the tables that relocations go through
+    allocate_common_symbols(&mut section_table, &mut symbol_table);
+    let got = allocate_got(
+        &mut section_table,
+        &mut symbol_table,
+        &references,
+        config.shared,
+    );
+    let dynamic_symbols = match config.shared {
+        true => symbol_table
+            .iter()
+            .map(|(index, _)| index)
+            .filter(|&index| symbol_table.is_global(index))
+            .collect(),
+        false => vec![],
+    };
+    let preemptible = dynamic_symbols.iter().copied().collect::<HashSet<_>>();
+    let plt = ProcedureLinkageTable::allocate(
+        &mut section_table,
+        &mut symbol_table,
+        &mut references,
+        |symbol| preemptible.contains(&symbol),
+    );
 
-        // Merge loadable sections
-        section_table.merge(object.section_table);
+    // Assign final addresses; nothing can be relocated without them
+    if let Err(error) = section_table.assign_addresses(config) {
+        errors.push(error.into());
+        return Err(diagnostics(errors, warnings));
+    }
+    let position_independent = config.pie || config.shared;
+    let mut dynamic_relocations = vec![];
+    if let Some(got) = &got {
+        fill_got(
+            got,
+            &mut section_table,
+            &symbol_table,
+            config,
+            &mut dynamic_relocations,
+        );
     }
+    if let Some(plt) = &plt {
+        dynamic_relocations.extend(plt.fill(&mut section_table));
+    }
+
+    // Find the entry point, which a shared object may not have
+    let entry = match entry_address(&config.entry, &section_table, &symbol_table) {
+        Ok(entry) => entry,
+        Err(_) if config.shared => 0,
+        Err(error) => {
+            errors.push(error);
+            0
+        }
+    };
 
     // Relocate references
-    for reference in references {
+    let high_parts = HighPartRelocations::new(&references);
+    let differences = LabelDifferences::new(&references);
+    for reference in &references {
+        // The loader binds preemptible symbols, except in GOT entries and label
+        // differences
+        let through_got = matches!(
+            reference.typ.formula(),
+            RelocationFormula::GotEntry | RelocationFormula::GotEntryPcRelative
+        );
+        let is_difference = differences.contains(reference);
+        if preemptible.contains(&reference.symbol) && !through_got && !is_difference {
+            match dynamic_relocation(
+                reference,
+                None,
+                &section_table,
+                &symbol_table,
+                &mut warnings,
+            ) {
+                Ok(dynamic_relocation) => dynamic_relocations.extend(dynamic_relocation),
+                Err(error) => errors.push(error),
+            }
+            continue;
+        }
+
         // Calculate new reference value
-        let new_reference_value = relocate_reference(&reference, &symbol_table, &section_table)?;
+        let new_reference_value = match relocate_reference(
+            reference,
+            &high_parts,
+            got.as_ref(),
+            &symbol_table,
+            &section_table,
+        ) {
+            Ok(new_reference_value) => new_reference_value,
+            Err(error) => {
+                errors.push(error.into());
+                continue;
+            }
+        };
+
+        // The distance to an absolute symbol changes with the load address
+        let symbol = symbol_table.get(reference.symbol);
+        if position_independent
+            && matches!(symbol.value, SymbolValue::Absolute(_))
+            && matches!(
+                reference.typ.formula(),
+                RelocationFormula::PcRelative | RelocationFormula::PagePcRelative
+            )
+        {
+            errors.push(LinkError::PositionDependentRelocation {
+                symbol: symbol.name.to_string(),
+                origin: reference.origin().clone(),
+            });
+            continue;
+        }
+
+        // Absolute addresses only hold if the image is loaded at its link-time address
+        if position_independent
+            && reference.typ.formula() == RelocationFormula::Absolute
+            && !is_difference
+        {
+            match dynamic_relocation(
+                reference,
+                Some(new_reference_value),
+                &section_table,
+                &symbol_table,
+                &mut warnings,
+            ) {
+                Ok(dynamic_relocation) => dynamic_relocations.extend(dynamic_relocation),
+                Err(error) => {
+                    errors.push(error);
+                    continue;
+                }
+            }
+        }
 
         // Update the reference value in the corresponding section
-        section_table.section_mut(reference.section)
-            [reference.offset..reference.offset + address_len]
-            .copy_from_slice(&new_reference_value.to_le_bytes()[..address_len]);
+        let section = section_table.section_mut(reference.section);
+        let Some(bytes) = section.data.bytes_mut() else {
+            errors.push(LinkError::ZeroFillRelocation {
+                section: section.name.clone(),
+            });
+            continue;
+        };
+        let field = reference.typ.field();
+        let endianness = match field.layout.is_instruction() {
+            true => Endianness::Little,
+            false => config.endianness,
+        };
+        let data = &mut bytes[reference.offset..reference.offset + field.width];
+        endianness.write(
+            data,
+            field.insert(endianness.read(data), new_reference_value),
+        );
+    }
+
+    let diagnostics = diagnostics(errors, warnings);
+    if diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error)
+    {
+        return Err(diagnostics);
+    }
+    dynamic_relocations.sort_by_key(|relocation| relocation.address);
+    Ok(InMemoryExecutableObject {
+        section_table,
+        symbol_table,
+        entry,
+        removed_sections,
+        architecture: config.architecture,
+        endianness: config.endianness,
+        pie: config.pie,
+        shared: config.shared,
+        soname: config.soname.clone(),
+        dynamic_symbols,
+        dynamic_relocations,
+        diagnostics,
+    })
+}
+
+/// The places that hold the difference of two addresses (RISC-V `ADD`/`SUB`, and
+/// `SET` paired with `SUB`), which does not depend on where the image is loaded.
+struct LabelDifferences {
+    subtracted: HashSet<(InMemorySectionIndex, usize)>,
+}
+impl LabelDifferences {
+    fn new(references: &[ResolvingRelocation<InMemorySectionIndex>]) -> Self {
+        let subtracted = references
+            .iter()
+            .filter(|reference| reference.typ.field().layout == FieldLayout::Subtract)
+            .map(|reference| (reference.section, reference.offset))
+            .collect();
+        Self { subtracted }
+    }
+
+    fn contains(&self, reference: &ResolvingRelocation<InMemorySectionIndex>) -> bool {
+        reference.typ.field().layout == FieldLayout::Add
+            || self
+                .subtracted
+                .contains(&(reference.section, reference.offset))
+    }
+}
+
+/// The dynamic relocation that an absolute relocation becomes in
+/// position-independent output: the address of the symbol if it is preemptible,
+/// as `value` is then `None`, or else the load address plus `value`.
+///
+/// Only address-sized words can be filled in at load time; those outside writable
+/// sections are text relocations, which are warned about.
+fn dynamic_relocation(
+    reference: &ResolvingRelocation<InMemorySectionIndex>,
+    value: Option<usize>,
+    section_table: &InMemoryLoadableSectionTable,
+    symbol_table: &ResolvingSymbolTable<InMemorySectionIndex>,
+    warnings: &mut Vec<LinkError>,
+) -> Result<Option<DynamicRelocation>, LinkError> {
+    let symbol = symbol_table.get(reference.symbol);
+    // Undefined weak symbols are 0 and absolute symbols keep their value wherever the
+    // image is loaded
+    if value.is_some() && !matches!(symbol.value, SymbolValue::Defined(_)) {
+        return Ok(None);
+    }
+    let field = reference.typ.field();
+    let address_size = GlobalOffsetTable::<InMemorySectionIndex>::ENTRY_SIZE;
+    if reference.typ.formula() != RelocationFormula::Absolute
+        || field.width != address_size
+        || field.layout != FieldLayout::Whole
+    {
+        return Err(LinkError::PositionDependentRelocation {
+            symbol: symbol.name.to_string(),
+            origin: reference.origin().clone(),
+        });
+    }
+    if section_table.section(reference.section).kind != SectionKind::Data {
+        warnings.push(LinkError::TextRelocation {
+            symbol: symbol.name.to_string(),
+            origin: reference.origin().clone(),
+        });
+    }
+    let address = section_table.address(reference.section) + reference.offset;
+    Ok(Some(match value {
+        Some(value) => DynamicRelocation {
+            address,
+            kind: DynamicRelocationKind::Relative,
+            addend: value,
+        },
+        None => DynamicRelocation {
+            address,
+            kind: DynamicRelocationKind::Absolute(reference.symbol),
+            addend: reference.addend as usize,
+        },
+    }))
+}
+
+/// The sections folded into another one, and the placements of the input sections
+/// merged so far.
+struct FoldedSections {
+    folds: HashMap<InputSection, InputSection>,
+    placements: HashMap<InputSection, SectionPlacement<InMemorySectionIndex>>,
+}
+
+/// Merges the object at `position` in link order.
+fn resolve_object<'name>(
+    position: usize,
+    object: InMemoryRelocatableObject<'name>,
+    folded_sections: &mut FoldedSections,
+    section_table: &mut InMemoryLoadableSectionTable,
+    symbol_table: &mut ResolvingSymbolTable<'name, InMemorySectionIndex>,
+    references: &mut Vec<ResolvingRelocation<InMemorySectionIndex>>,
+    errors: &mut Vec<LinkError>,
+) {
+    // Merge loadable sections
+    let sections = object
+        .section_table
+        .iter()
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    let mut section_mapping = section_table.merge(object.name, object.section_table);
+    // Folded sections take the placement of the section they were folded into
+    let FoldedSections { folds, placements } = folded_sections;
+    for index in sections {
+        if let Some(representative) = folds.get(&(position, index)) {
+            section_mapping.redirect(index, placements[representative]);
+        }
+        placements.insert((position, index), section_mapping.placement(index).unwrap());
+    }
+
+    // Resolve unloadable sections
+    errors.extend(
+        resolve_unloadable_sections(
+            object.name,
+            &section_mapping,
+            symbol_table,
+            object.symbol_table,
+            references,
+            object.references,
+        )
+        .into_iter()
+        .map(LinkError::from),
+    );
+}
+
+/// Selects every valid object, and the archive members that define a global
+/// symbol undefined at their position, in link order.
+fn select_objects<'name>(
+    inputs: Vec<LinkInput<'name>>,
+    architecture: Architecture,
+    errors: &mut Vec<LinkError>,
+) -> Vec<InMemoryRelocatableObject<'name>> {
+    let mut objects = vec![];
+    let mut symbols = SelectedSymbols::default();
+    for input in inputs.into_iter() {
+        match input {
+            LinkInput::Object(object) => {
+                select_object(object, architecture, &mut symbols, &mut objects, errors)
+            }
+            LinkInput::Archive(archive) => select_members(
+                vec![archive],
+                architecture,
+                &mut symbols,
+                &mut objects,
+                errors,
+            ),
+            LinkInput::Group(archives) => {
+                select_members(archives, architecture, &mut symbols, &mut objects, errors)
+            }
+        }
+    }
+    objects
+}
+
+fn select_object<'name>(
+    object: InMemoryRelocatableObject<'name>,
+    architecture: Architecture,
+    symbols: &mut SelectedSymbols<'name>,
+    objects: &mut Vec<InMemoryRelocatableObject<'name>>,
+    errors: &mut Vec<LinkError>,
+) {
+    if let Err(error) = object.validate(architecture) {
+        errors.push(error.into());
+        return;
+    }
+    symbols.add(&object);
+    objects.push(object);
+}
+
+/// Selects archive members that define an undefined global symbol, until none does.
+fn select_members<'name>(
+    archives: Vec<InMemoryArchive<'name>>,
+    architecture: Architecture,
+    symbols: &mut SelectedSymbols<'name>,
+    objects: &mut Vec<InMemoryRelocatableObject<'name>>,
+    errors: &mut Vec<LinkError>,
+) {
+    // Members are taken out once selected, and each archive looks up the undefined
+    // symbols from the one after the last it looked up
+    let mut archives = archives
+        .into_iter()
+        .map(|archive| {
+            let members = archive.members.into_iter().map(Some).collect::<Vec<_>>();
+            (archive.symbol_index, members, 0)
+        })
+        .collect::<Vec<_>>();
+
+    loop {
+        let mut extracted = false;
+        for (symbol_index, members, next) in archives.iter_mut() {
+            while let Some(&name) = symbols.undefined.get(*next) {
+                *next += 1;
+                if !symbols.is_undefined(name) {
+                    continue;
+                }
+                let Some(member) = symbol_index
+                    .get(name)
+                    .and_then(|&index| members[index].take())
+                else {
+                    continue;
+                };
+                match (member.parse)() {
+                    Ok(object) => select_object(object, architecture, symbols, objects, errors),
+                    Err(error) => errors.push(LinkError::InvalidMember {
+                        member: member.name.to_string(),
+                        error,
+                    }),
+                }
+                extracted = true;
+            }
+        }
+        if !extracted {
+            return;
+        }
+    }
+}
+
+/// The global symbols of the selected objects.
+#[derive(Default)]
+struct SelectedSymbols<'name> {
+    /// Whether each symbol is defined, and whether a global reference requires it.
+    states: HashMap<&'name str, (bool, bool)>,
+    /// The symbols in the order they became required while undefined; they stay
+    /// listed once defined.
+    undefined: Vec<&'name str>,
+}
+impl<'name> SelectedSymbols<'name> {
+    fn add(&mut self, object: &InMemoryRelocatableObject<'name>) {
+        for (_, symbol) in object.symbol_table.iter() {
+            if symbol.binding == SymbolBinding::Local {
+                continue;
+            }
+            let (defined, required) = self.states.entry(symbol.name).or_default();
+            let was_undefined = *required && !*defined;
+            match symbol.value {
+                SymbolValue::Undefined => *required |= symbol.binding == SymbolBinding::Global,
+                SymbolValue::Defined(_) | SymbolValue::Common { .. } | SymbolValue::Absolute(_) => {
+                    *defined = true
+                }
+            }
+            if *required && !*defined && !was_undefined {
+                self.undefined.push(symbol.name);
+            }
+        }
+    }
+    /// Whether the symbol is required but not defined.
+    fn is_undefined(&self, name: &str) -> bool {
+        let (defined, required) = self.states[name];
+        required && !defined
+    }
+}
+
+fn entry_address(
+    entry: &EntryPoint,
+    section_table: &InMemoryLoadableSectionTable,
+    symbol_table: &ResolvingSymbolTable<InMemorySectionIndex>,
+) -> Result<usize, LinkError> {
+    let symbol_name = match entry {
+        EntryPoint::Symbol(symbol_name) => symbol_name,
+        EntryPoint::Address(address) => return Ok(*address),
+    };
+    let Some(SymbolValue::Defined(definition)) = symbol_table
+        .get_by_name(symbol_name)
+        .map(|symbol| symbol.value)
+    else {
+        return Err(LinkError::EntryNotDefined {
+            symbol: symbol_name.clone(),
+        });
+    };
+    Ok(section_table.address(definition.section) + definition.offset)
+}
+
+/// Stands in for the object that contributes the space of common symbols.
+const COMMON_OBJECT: &str = "<common>";
+
+/// Allocates the common symbols left without a real definition in `.bss`.
+fn allocate_common_symbols(
+    section_table: &mut InMemoryLoadableSectionTable,
+    symbol_table: &mut ResolvingSymbolTable<InMemorySectionIndex>,
+) {
+    let mut common_symbols = vec![];
+    let mut len: usize = 0;
+    let mut max_alignment = 1;
+    for (index, symbol) in symbol_table.iter() {
+        let SymbolValue::Common { size, alignment } = symbol.value else {
+            continue;
+        };
+        let offset = len.next_multiple_of(alignment);
+        common_symbols.push((index, *symbol, offset, size));
+        len = offset + size;
+        max_alignment = max_alignment.max(alignment);
+    }
+    if common_symbols.is_empty() {
+        return;
+    }
+
+    // Merge the allocated space like any other input section
+    let mut common_section_table = InMemoryLoadableSectionTable::new();
+    let common_section = common_section_table.add_zero_fill_section(
+        ".bss.common",
+        SectionKind::Data,
+        max_alignment,
+        len,
+    );
+    let section_mapping = section_table.merge(COMMON_OBJECT, common_section_table);
+    let placement = section_mapping.placement(common_section).unwrap();
+
+    for (index, symbol, offset, size) in common_symbols {
+        symbol_table.replace(
+            index,
+            Symbol {
+                value: SymbolValue::Defined(SymbolDefinition {
+                    section: placement.section,
+                    offset: placement.offset + offset,
+                    size,
+                }),
+                ..symbol
+            },
+        );
+    }
+}
+
+/// Stands in for the object that contributes the global offset table.
+const GOT_OBJECT: &str = "<got>";
+
+/// Labels the global offset table for code that addresses data relative to it.
+const GOT_SYMBOL: &str = "_GLOBAL_OFFSET_TABLE_";
+
+/// Allocates an entry in `.got` for each symbol that relocations reach through the
+/// global offset table, if any relocation or `_GLOBAL_OFFSET_TABLE_` refers to it.
+///
+/// In a shared object, the loader fills in the entries of global symbols.
+fn allocate_got(
+    section_table: &mut InMemoryLoadableSectionTable,
+    symbol_table: &mut ResolvingSymbolTable<InMemorySectionIndex>,
+    references: &[ResolvingRelocation<InMemorySectionIndex>],
+    shared: bool,
+) -> Option<GlobalOffsetTable<InMemorySectionIndex>> {
+    let got_symbol = symbol_table
+        .get_index_by_name(GOT_SYMBOL)
+        .filter(|&index| symbol_table.get(index).value == SymbolValue::Undefined);
+    if got_symbol.is_none() && !GlobalOffsetTable::is_used(references) {
+        return None;
+    }
+
+    // Merge the table like any other input section; it is filled once addresses are known
+    let symbols = GlobalOffsetTable::symbols(references);
+    let entry_size = GlobalOffsetTable::<InMemorySectionIndex>::ENTRY_SIZE;
+    let mut got_section_table = InMemoryLoadableSectionTable::new();
+    let got_section = got_section_table.add_section(
+        ".got",
+        SectionKind::Data,
+        entry_size,
+        vec![0; symbols.len() * entry_size],
+    );
+    let section_mapping = section_table.merge(GOT_OBJECT, got_section_table);
+    let placement = section_mapping.placement(got_section).unwrap();
+
+    // The table is private to the output
+    if let Some(index) = got_symbol {
+        let symbol = Symbol {
+            value: SymbolValue::Defined(SymbolDefinition {
+                section: placement.section,
+                offset: placement.offset,
+                size: 0,
+            }),
+            binding: SymbolBinding::Local,
+            ..*symbol_table.get(index)
+        };
+        symbol_table.redefine(index, symbol, &GOT_OBJECT.into());
+    }
+    Some(GlobalOffsetTable::new(placement, &symbols, |symbol| {
+        shared && symbol_table.is_global(symbol)
+    }))
+}
+
+/// Writes the final address of each symbol into its entry.
+///
+/// In position-independent output, entries are also filled in at load time.
+fn fill_got(
+    got: &GlobalOffsetTable<InMemorySectionIndex>,
+    section_table: &mut InMemoryLoadableSectionTable,
+    symbol_table: &ResolvingSymbolTable<InMemorySectionIndex>,
+    config: &LinkConfig,
+    dynamic_relocations: &mut Vec<DynamicRelocation>,
+) {
+    let entry_size = GlobalOffsetTable::<InMemorySectionIndex>::ENTRY_SIZE;
+    let section_address = section_table.address(got.placement().section);
+    let values = got.values(symbol_table, section_table).collect::<Vec<_>>();
+    let section = section_table.section_mut(got.placement().section);
+    let bytes = section.data.bytes_mut().unwrap();
+    for (offset, value) in values {
+        let address = section_address + offset;
+        let value = match value {
+            GotValue::Address(value) => {
+                if config.pie || config.shared {
+                    dynamic_relocations.push(DynamicRelocation {
+                        address,
+                        kind: DynamicRelocationKind::Relative,
+                        addend: value,
+                    });
+                }
+                value
+            }
+            GotValue::Null => 0,
+            GotValue::Absolute(value) => value,
+            GotValue::Preemptible(symbol) => {
+                dynamic_relocations.push(DynamicRelocation {
+                    address,
+                    kind: DynamicRelocationKind::GotEntry(symbol),
+                    addend: 0,
+                });
+                0
+            }
+        };
+        config
+            .endianness
+            .write(&mut bytes[offset..offset + entry_size], value);
+    }
+}
+
+/// Diagnostics beyond this many for the same problem with the same symbol are
+/// only counted.
+const DIAGNOSTICS_PER_SYMBOL: usize = 3;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// A problem that does not fail the link.
+    Warning,
+    Error,
+}
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
     }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub error: LinkError,
+    /// How many more times the problem occurs with the same symbol.
+    pub more: usize,
+}
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.error)?;
+        if self.more > 0 {
+            write!(f, " (referenced {} more times)", self.more)?;
+        }
+        Ok(())
+    }
+}
+impl From<LinkError> for Diagnostic {
+    fn from(error: LinkError) -> Self {
+        Self {
+            severity: Severity::Error,
+            error,
+            more: 0,
+        }
+    }
+}
 
-    Ok(section_table)
+/// Turns errors and warnings into diagnostics, keeping the first few of each symbol.
+fn diagnostics(errors: Vec<LinkError>, warnings: Vec<LinkError>) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = vec![];
+    // The number of diagnostics of each group and the position of the last one
+    let mut groups: HashMap<_, (usize, usize)> = HashMap::new();
+    let errors = errors.into_iter().map(|error| (Severity::Error, error));
+    let warnings = warnings.into_iter().map(|error| (Severity::Warning, error));
+    for (severity, error) in errors.chain(warnings) {
+        let diagnostic = Diagnostic {
+            severity,
+            error,
+            more: 0,
+        };
+        let Some(group) = diagnostic.error.group() else {
+            diagnostics.push(diagnostic);
+            continue;
+        };
+        match groups.entry(group) {
+            Entry::Occupied(entry) => {
+                let (count, last) = entry.into_mut();
+                if *count == DIAGNOSTICS_PER_SYMBOL {
+                    diagnostics[*last].more += 1;
+                    continue;
+                }
+                *count += 1;
+                *last = diagnostics.len();
+            }
+            Entry::Vacant(entry) => {
+                entry.insert((1, diagnostics.len()));
+            }
+        }
+        diagnostics.push(diagnostic);
+    }
+    diagnostics
 }
 
 #[derive(Debug, Error, PartialEq, Eq, Clone)]
@@ -52,6 +756,53 @@ pub enum LinkError {
     ResolveError(ResolveError<InMemorySectionIndex>),
     #[error("Relocation error: {0}")]
     RelocationError(RelocationError),
+    #[error("Layout error: {0}")]
+    LayoutError(LayoutError),
+    #[error("Invalid object: {0}")]
+    ValidationError(ValidationError),
+    #[error("Entry symbol `{symbol}` not defined")]
+    EntryNotDefined { symbol: String },
+    #[error("Relocation in zero-fill section `{section}`")]
+    ZeroFillRelocation { section: String },
+    #[error(
+        "Relocation against `{symbol}` in {origin} cannot be used in position-independent output"
+    )]
+    PositionDependentRelocation {
+        symbol: String,
+        origin: RelocationOrigin,
+    },
+    #[error("Invalid archive member `{member}`: {error}")]
+    InvalidMember { member: String, error: String },
+    #[error("Relocation against `{symbol}` in {origin} makes the loader write to read-only code")]
+    TextRelocation {
+        symbol: String,
+        origin: RelocationOrigin,
+    },
+}
+impl LinkError {
+    /// Identifies errors about the same problem with the same symbol.
+    fn group(
+        &self,
+    ) -> Option<(
+        String,
+        Discriminant<Self>,
+        Option<Discriminant<RelocationError>>,
+    )> {
+        let (symbol, relocation_error) = match self {
+            LinkError::ResolveError(ResolveError::ConflictSymbols { symbol, .. })
+            | LinkError::PositionDependentRelocation { symbol, .. }
+            | LinkError::TextRelocation { symbol, .. } => (symbol, None),
+            LinkError::RelocationError(
+                error @ (RelocationError::SymbolNotDefined { symbol, .. }
+                | RelocationError::NoGlobalOffsetTable { symbol, .. }
+                | RelocationError::MissingHighPart { symbol, .. }
+                | RelocationError::Overflow { symbol, .. }
+                | RelocationError::Misaligned { symbol, .. }),
+            ) => (symbol, Some(mem::discriminant(error))),
+            _ => return None,
+        };
+        Some((symbol.clone(), mem::discriminant(self), relocation_error))
+    }
 }
 impl From<ResolveError<InMemorySectionIndex>> for LinkError {
     fn from(value: ResolveError<InMemorySectionIndex>) -> Self {
@@ -63,32 +814,68 @@ impl From<RelocationError> for LinkError {
         Self::RelocationError(value)
     }
 }
+impl From<ValidationError> for LinkError {
+    fn from(value: ValidationError) -> Self {
+        Self::ValidationError(value)
+    }
+}
+impl From<LayoutError> for LinkError {
+    fn from(value: LayoutError) -> Self {
+        Self::LayoutError(value)
+    }
+}
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use crate::{
-        core::models::{
-            Relocation, RelocationType, Symbol, SymbolDefinition, SymbolTable, SymbolValue,
+        core::{
+            models::{
+                Aarch64RelocationType, Architecture, LoadableSectionTable, Relocation,
+                RelocationOrigin, RelocationType, RiscVRelocationType, Symbol, SymbolBinding,
+                SymbolDefinition, SymbolTable, SymbolValue, X86_64RelocationType,
+            },
+            RelocationError, ResolveError,
         },
         in_memory::{
             link,
-            models::{InMemoryLoadableSectionTable, InMemoryRelocatableObject},
+            models::{
+                Contribution, DynamicRelocation, DynamicRelocationKind, Endianness, EntryPoint,
+                Icf, InMemoryArchive, InMemoryArchiveMember, InMemoryExecutableObject,
+                InMemoryLoadableSectionTable, InMemoryRelocatableObject, LayoutError, LinkConfig,
+                RemovedSection, SectionData, SectionKind, ValidationError,
+            },
+            Diagnostic, LinkError, LinkInput, Severity,
         },
     };
 
-    const ADDRESS_LEN: usize = 4;
+    /// Places `.text` at address 0 and packs sections back to back.
+    fn config() -> LinkConfig {
+        LinkConfig {
+            image_base: 0,
+            page_size: None,
+            entry: EntryPoint::Address(0),
+            ..Default::default()
+        }
+    }
 
     #[test]
     fn main_sum_ok() {
-        let objects = vec![main_o(), sum_o()];
+        let objects = vec![LinkInput::Object(main_o()), LinkInput::Object(sum_o())];
 
         // Link
-        let section_table = link(objects, ADDRESS_LEN).unwrap();
+        let section_table = link(objects, &config()).unwrap().section_table;
 
         // Check result
         assert_eq!(
-            section_table.sections().nth(0).unwrap(),
-            &vec![
+            section_table
+                .section_by_name(".text")
+                .unwrap()
+                .data
+                .bytes()
+                .unwrap(),
+            vec![
                 // 0000000000000000 <main>
                 0x48, 0x83, 0xec, 0x08, // sub rsp, 8
                 0xbe, 0x02, 0x00, 0x00, 0x00, // mov esi, 2
@@ -109,8 +896,13 @@ mod tests {
             ]
         );
         assert_eq!(
-            section_table.sections().nth(1).unwrap(),
-            &vec![
+            section_table
+                .section_by_name(".data")
+                .unwrap()
+                .data
+                .bytes()
+                .unwrap(),
+            vec![
                 // 0000000000000033 <array>
                 0x01, 0x00, 0x00, 0x00, // array: .int 1
                 0x02, 0x00, 0x00, 0x00, // .int 2
@@ -118,17 +910,51 @@ mod tests {
         );
     }
 
+    #[test]
+    fn byte_order() {
+        for (endianness, array, sum) in [
+            (
+                Endianness::Little,
+                [0x33, 0x00, 0x00, 0x00],
+                [0x05, 0x00, 0x00, 0x00],
+            ),
+            (
+                Endianness::Big,
+                [0x00, 0x00, 0x00, 0x33],
+                [0x00, 0x00, 0x00, 0x05],
+            ),
+        ] {
+            let objects = vec![LinkInput::Object(main_o()), LinkInput::Object(sum_o())];
+
+            // Link
+            let mut config = config();
+            config.endianness = endianness;
+            let section_table = link(objects, &config).unwrap().section_table;
+
+            // Check result
+            let text = section_table.section_by_name(".text").unwrap();
+            let text = text.data.bytes().unwrap();
+            assert_eq!(&text[0xa..0xe], &array);
+            assert_eq!(&text[0xf..0x13], &sum);
+        }
+    }
+
     #[test]
     fn sum_main_ok() {
-        let objects = vec![sum_o(), main_o()];
+        let objects = vec![LinkInput::Object(sum_o()), LinkInput::Object(main_o())];
 
         // Link
-        let section_table = link(objects, ADDRESS_LEN).unwrap();
+        let section_table = link(objects, &config()).unwrap().section_table;
 
         // Check result
         assert_eq!(
-            section_table.sections().nth(0).unwrap(),
-            &vec![
+            section_table
+                .section_by_name(".text")
+                .unwrap()
+                .data
+                .bytes()
+                .unwrap(),
+            vec![
                 // 0000000000000000 <sum>
                 0xb8, 0x00, 0x00, 0x00, 0x00, // mov eax, 0
                 0xba, 0x00, 0x00, 0x00, 0x00, // mov edx, 0
@@ -149,8 +975,13 @@ mod tests {
             ]
         );
         assert_eq!(
-            section_table.sections().nth(1).unwrap(),
-            &vec![
+            section_table
+                .section_by_name(".data")
+                .unwrap()
+                .data
+                .bytes()
+                .unwrap(),
+            vec![
                 // 0000000000000033 <array>
                 0x01, 0x00, 0x00, 0x00, // array: .int 1
                 0x02, 0x00, 0x00, 0x00, // .int 2
@@ -158,95 +989,2137 @@ mod tests {
         );
     }
 
-    fn main_o() -> InMemoryRelocatableObject<'static> {
-        let mut section_table = InMemoryLoadableSectionTable::new();
-        let mut symbol_table = SymbolTable::new();
-        let mut references = Vec::new();
+    #[test]
+    fn merge_by_name() {
+        let objects = vec![
+            LinkInput::Object(main_o()),
+            LinkInput::Object(sum_with_rodata_o()),
+        ];
 
-        // Add loadable sections
-        let text_section = section_table.add_section(vec![
-            0x48, 0x83, 0xec, 0x08, // sub rsp, 8
-            0xbe, 0x02, 0x00, 0x00, 0x00, // mov esi, 2
-            0xbf, 0x00, 0x00, 0x00, 0x00, // mov edi, array
-            0xe8, 0x00, 0x00, 0x00, 0x00, // call sum
-            0x48, 0x83, 0xc4, 0x08, // add rsp, 8
-            0xc3, // ret
-        ]);
-        let data_section = section_table.add_section(vec![
-            0x01, 0x00, 0x00, 0x00, // array: .int 1
-            0x02, 0x00, 0x00, 0x00, // .int 2
-        ]);
+        // Link
+        let section_table = link(objects, &config()).unwrap().section_table;
 
-        // Add symbols
-        let sum_symbol = symbol_table.add(Symbol {
-            name: "sum",
-            value: SymbolValue::Undefined,
-        });
-        let array_symbol = symbol_table.add(Symbol {
-            name: "array",
-            value: SymbolValue::Defined(SymbolDefinition {
-                section: data_section,
-                offset: 0,
-                size: 8,
-            }),
-        });
+        // Check result
+        let names = section_table
+            .sections()
+            .map(|section| section.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec![".text", ".data", ".rodata"]);
+        let text = section_table
+            .section_by_name(".text")
+            .unwrap()
+            .data
+            .bytes()
+            .unwrap();
+        assert_eq!(text.len(), 0x33);
+        assert_eq!(
+            &text[0xe..0x13],
+            &[
+                0xe8, 0x05, 0x00, 0x00, 0x00, // call +9 <sum>
+            ]
+        );
+        assert_eq!(
+            section_table
+                .section_by_name(".rodata")
+                .unwrap()
+                .data
+                .bytes()
+                .unwrap(),
+            b"sum\0"
+        );
+    }
 
-        // Add references
-        references.push(Relocation {
-            offset: 0xf,
-            symbol: sum_symbol,
-            typ: RelocationType::PcRelative,
-            addend: -4,
-            section: text_section,
-        });
-        references.push(Relocation {
-            offset: 0xa,
-            symbol: array_symbol,
-            typ: RelocationType::Absolute,
-            addend: 0,
-            section: text_section,
-        });
+    #[test]
+    fn zero_fill_after_data() {
+        let objects = vec![
+            LinkInput::Object(counter_o()),
+            LinkInput::Object(main_o()),
+            LinkInput::Object(sum_o()),
+        ];
 
-        InMemoryRelocatableObject {
-            section_table,
-            symbol_table,
-            references,
-        }
+        // Link
+        let section_table = link(objects, &config()).unwrap().section_table;
+
+        // Check result
+        let bss = section_table.section_by_name(".bss").unwrap();
+        assert_eq!(bss.data, SectionData::ZeroFill(16));
+        let text = section_table
+            .section_by_name(".text")
+            .unwrap()
+            .data
+            .bytes()
+            .unwrap();
+        assert_eq!(
+            &text[..9],
+            &[
+                0x83, 0x04, 0x25, 0x44, 0x00, 0x00, 0x00, 0x01, // add dword ptr [counter], 1
+                0xc3, // ret
+            ]
+        );
+        assert_eq!(
+            &text[0x12..0x17],
+            &[
+                0xbf, 0x3c, 0x00, 0x00, 0x00, // mov edi, array
+            ]
+        );
     }
 
-    fn sum_o() -> InMemoryRelocatableObject<'static> {
-        let mut section_table = InMemoryLoadableSectionTable::new();
-        let mut symbol_table = SymbolTable::new();
-        let references = Vec::new();
+    #[test]
+    fn aligned_merge() {
+        let objects = vec![
+            LinkInput::Object(main_o()),
+            LinkInput::Object(aligned_sum_o(16)),
+        ];
 
-        // Add loadable sections
-        let text_section = section_table.add_section(vec![
-            0xb8, 0x00, 0x00, 0x00, 0x00, // mov eax, 0
-            0xba, 0x00, 0x00, 0x00, 0x00, // mov edx, 0
-            0xeb, 0x09, // jmp +9
-            0x48, 0x63, 0xca, // movsxd rcx, edx
-            0x03, 0x04, 0x8f, // add eax, [rdi + rcx * 4]
-            0x83, 0xc2, 0x01, // add edx, 1
-            0x39, 0xf2, // cmp edx, esi
-            0x7c, 0xf3, // jl -13
-            0xf3, 0xc3, // rep ret
-        ]);
-        let _data_section = section_table.add_section(vec![]);
+        // Link
+        let section_table = link(objects, &config()).unwrap().section_table;
 
-        // Add symbols
-        let _sum_symbol = symbol_table.add(Symbol {
-            name: "sum",
-            value: SymbolValue::Defined(SymbolDefinition {
-                section: text_section,
-                offset: 0,
-                size: 0,
-            }),
-        });
+        // Check result
+        let text = section_table.section_by_name(".text").unwrap();
+        assert_eq!(text.alignment, 16);
+        let text = text.data.bytes().unwrap();
+        assert_eq!(
+            &text[0x9..0x20],
+            &[
+                0xbf, 0x3b, 0x00, 0x00, 0x00, // mov edi, array
+                0xe8, 0x0d, 0x00, 0x00, 0x00, // call +0x0d <sum>
+                0x48, 0x83, 0xc4, 0x08, // add rsp, 8
+                0xc3, // ret
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // padding
+            ]
+        );
+        assert_eq!(text[0x20], 0xb8);
+    }
 
-        // Add references
-        // None
+    #[test]
+    fn contributions() {
+        let objects = vec![
+            LinkInput::Object(main_o()),
+            LinkInput::Object(aligned_sum_o(16)),
+        ];
+
+        // Link
+        let section_table = link(objects, &config()).unwrap().section_table;
+
+        // Check result
+        let text = section_table.section_by_name(".text").unwrap();
+        assert_eq!(
+            text.contributions,
+            [
+                Contribution {
+                    object: "main.o".to_string(),
+                    section: ".text".to_string(),
+                    offset: 0,
+                    size: 0x18,
+                    padding: 0,
+                },
+                Contribution {
+                    object: "sum.o".to_string(),
+                    section: ".text".to_string(),
+                    offset: 0x20,
+                    size: 0x1b,
+                    padding: 8,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn image_base() {
+        let objects = vec![LinkInput::Object(main_o()), LinkInput::Object(sum_o())];
+
+        // Link
+        let config = LinkConfig {
+            entry: EntryPoint::Address(0),
+            ..Default::default()
+        };
+        let section_table = link(objects, &config).unwrap().section_table;
+
+        // Check result
+        let text = section_table
+            .section_by_name(".text")
+            .unwrap()
+            .data
+            .bytes()
+            .unwrap();
+        assert_eq!(
+            &text[0x9..0x13],
+            &[
+                0xbf, 0x33, 0x10, 0x40, 0x00, // mov edi, array
+                0xe8, 0x05, 0x00, 0x00, 0x00, // call +9 <sum>
+            ]
+        );
+    }
+
+    #[test]
+    fn section_start() {
+        let objects = vec![LinkInput::Object(main_o()), LinkInput::Object(sum_o())];
+
+        // Link
+        let mut config = config();
+        config.section_starts.insert(".data".to_string(), 0x600000);
+        let section_table = link(objects, &config).unwrap().section_table;
+
+        // Check result
+        let text = section_table
+            .section_by_name(".text")
+            .unwrap()
+            .data
+            .bytes()
+            .unwrap();
+        assert_eq!(
+            &text[0x9..0xe],
+            &[
+                0xbf, 0x00, 0x00, 0x60, 0x00, // mov edi, array
+            ]
+        );
+        let addresses = section_table
+            .indices()
+            .map(|index| section_table.address(index))
+            .collect::<Vec<_>>();
+        assert_eq!(addresses, vec![0, 0x600000]);
+    }
+
+    #[test]
+    fn overlapping_section_starts() {
+        let objects = vec![LinkInput::Object(main_o()), LinkInput::Object(sum_o())];
+
+        // Link
+        let mut config = config();
+        config.section_starts.insert(".data".to_string(), 0x10);
+        let result = link(objects, &config);
+
+        // Check result
+        assert_eq!(
+            result.err(),
+            Some(vec![LinkError::LayoutError(LayoutError::Overlap {
+                first: ".text".to_string(),
+                second: ".data".to_string(),
+            })
+            .into()])
+        );
+    }
+
+    #[test]
+    fn absolute_overflow() {
+        let objects = vec![LinkInput::Object(main_o()), LinkInput::Object(sum_o())];
+
+        // Link
+        let mut config = config();
+        config
+            .section_starts
+            .insert(".data".to_string(), 0x1_0000_0000);
+        let result = link(objects, &config);
+
+        // Check result
+        let Err(diagnostics) = result else {
+            panic!("`array` does not overflow");
+        };
+        let [Diagnostic {
+            error:
+                LinkError::RelocationError(RelocationError::Overflow {
+                    symbol,
+                    origin,
+                    value,
+                }),
+            ..
+        }] = &diagnostics[..]
+        else {
+            panic!("`array` does not overflow");
+        };
+        assert_eq!(symbol, "array");
+        assert_eq!(origin.to_string(), "main.o:(.text+0xa)");
+        assert_eq!(*value, 0x1_0000_0000);
+    }
+
+    #[test]
+    fn pc_relative_overflow() {
+        for typ in [X86_64RelocationType::Pc32, X86_64RelocationType::Plt32] {
+            // `array` is addressed relative to the instruction, 3 GiB away
+            let mut object = main_o();
+            object.references[1].typ = RelocationType::X86_64(typ);
+            object.references[1].addend = -4;
+            let objects = vec![LinkInput::Object(object), LinkInput::Object(sum_o())];
+
+            // Link
+            let mut config = config();
+            config
+                .section_starts
+                .insert(".data".to_string(), 0xc000_0000);
+            let result = link(objects, &config);
+
+            // Check result
+            let Err(diagnostics) = result else {
+                panic!("`array` does not overflow");
+            };
+            let [Diagnostic {
+                error:
+                    LinkError::RelocationError(RelocationError::Overflow {
+                        symbol,
+                        origin,
+                        value,
+                    }),
+                ..
+            }] = &diagnostics[..]
+            else {
+                panic!("`array` does not overflow");
+            };
+            assert_eq!(symbol, "array");
+            assert_eq!(origin.to_string(), "main.o:(.text+0xa)");
+            assert_eq!(*value, 0xc000_0000 - 0xa - 4);
+        }
+    }
+
+    #[test]
+    fn got_relocations() {
+        let mut object = main_o();
+        object.references[1].typ = RelocationType::X86_64(X86_64RelocationType::GotPcRel);
+        object.references[1].addend = -4;
+        let got_symbol = object.symbol_table.add(Symbol {
+            name: "_GLOBAL_OFFSET_TABLE_",
+            value: SymbolValue::Undefined,
+            binding: SymbolBinding::Global,
+        });
+        object.references.push(Relocation {
+            offset: 0x5,
+            symbol: got_symbol,
+            typ: RelocationType::X86_64(X86_64RelocationType::GotPc32),
+            addend: 0,
+            section: object.references[1].section,
+        });
+        let objects = vec![LinkInput::Object(object), LinkInput::Object(sum_o())];
+
+        // Link
+        let executable = link(objects, &config()).unwrap();
+
+        // Check result: `.got` follows `.data` and holds the address of `array`
+        let section_table = executable.section_table;
+        let got = section_table.section_by_name(".got").unwrap();
+        assert_eq!(got.data.bytes().unwrap(), 0x33u64.to_le_bytes());
+        let text = section_table.section_by_name(".text").unwrap();
+        let text = text.data.bytes().unwrap();
+        // G + GOT + A - P
+        assert_eq!(text[0xa..0xe], 0x32u32.to_le_bytes());
+        // GOT + A - P
+        assert_eq!(text[0x5..0x9], 0x3bu32.to_le_bytes());
+        let got_symbol = executable.symbol_table.get_by_name("_GLOBAL_OFFSET_TABLE_");
+        let Some(SymbolValue::Defined(definition)) = got_symbol.map(|symbol| symbol.value) else {
+            panic!("`_GLOBAL_OFFSET_TABLE_` is defined");
+        };
+        assert_eq!(section_table.address(definition.section), 0x40);
+    }
+
+    #[test]
+    fn pie_relative_relocations() {
+        let config = LinkConfig {
+            pie: true,
+            ..config()
+        };
+
+        // `array` is loaded into a register with an absolute address
+        let objects = vec![LinkInput::Object(main_o()), LinkInput::Object(sum_o())];
+        let Err(diagnostics) = link(objects, &config) else {
+            panic!("`main.o` is position-dependent");
+        };
+        assert_eq!(
+            diagnostics,
+            [LinkError::PositionDependentRelocation {
+                symbol: "array".to_string(),
+                origin: RelocationOrigin {
+                    object: "main.o".into(),
+                    section: ".text".into(),
+                    offset: 0xa,
+                    symbol: None,
+                },
+            }
+            .into()]
+        );
+
+        // Through the GOT, with a pointer to `sum` in `array`
+        let mut object = main_o();
+        object.references[1].typ = RelocationType::X86_64(X86_64RelocationType::GotPcRel);
+        object.references[1].addend = -4;
+        let array = object.references[1].symbol;
+        let SymbolValue::Defined(definition) = object.symbol_table.get(array).value else {
+            panic!("`array` is defined");
+        };
+        object.references.push(Relocation {
+            offset: 0,
+            symbol: object.references[0].symbol,
+            typ: RelocationType::X86_64(X86_64RelocationType::Direct64),
+            addend: 0,
+            section: definition.section,
+        });
+        let objects = vec![LinkInput::Object(object), LinkInput::Object(sum_o())];
+        let executable = link(objects, &config).unwrap();
+
+        // Check result: the pointer and the GOT entry of `array` are adjusted; the
+        // image starts after the page kept for the headers
+        assert!(executable.pie);
+        assert_eq!(
+            executable.dynamic_relocations,
+            [
+                DynamicRelocation {
+                    address: 0x1033,
+                    kind: DynamicRelocationKind::Relative,
+                    addend: 0x1018,
+                },
+                DynamicRelocation {
+                    address: 0x1040,
+                    kind: DynamicRelocationKind::Relative,
+                    addend: 0x1033,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn pie_text_relocations() {
+        // `sum.o` with code that loads the absolute address of `sum`
+        let mut object = sum_o();
+        let text = object.section_table.add_section(
+            ".text",
+            SectionKind::Text,
+            1,
+            vec![
+                0x48, 0xb8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // movabs rax, sum
+                0xc3, // ret
+            ],
+        );
+        let (sum, _) = object
+            .symbol_table
+            .iter()
+            .find(|(_, symbol)| symbol.name == "sum")
+            .unwrap();
+        object.references.push(Relocation {
+            offset: 2,
+            symbol: sum,
+            typ: RelocationType::X86_64(X86_64RelocationType::Direct64),
+            addend: 0,
+            section: text,
+        });
+
+        // Link
+        let config = LinkConfig {
+            pie: true,
+            ..config()
+        };
+        let executable = link(vec![LinkInput::Object(object)], &config).unwrap();
+
+        // Check result: the loader patches the code, with a warning
+        assert_eq!(
+            executable.diagnostics,
+            [Diagnostic {
+                severity: Severity::Warning,
+                error: LinkError::TextRelocation {
+                    symbol: "sum".to_string(),
+                    origin: RelocationOrigin {
+                        object: "sum.o".into(),
+                        section: ".text".into(),
+                        offset: 2,
+                        symbol: None,
+                    },
+                },
+                more: 0,
+            }]
+        );
+        let [relocation] = &executable.dynamic_relocations[..] else {
+            panic!("the address of `sum` is relocated once");
+        };
+        assert_eq!(relocation.kind, DynamicRelocationKind::Relative);
+    }
+
+    #[test]
+    fn pie_label_differences() {
+        // `exit - loop` with an `ADD32`/`SUB32` pair and a `SET8`/`SUB8` pair, and a
+        // pointer to `exit`; the labels are local so that branches need no PLT
+        let object = || {
+            let mut object = compressed_branches_o();
+            for reference in &object.references[..2] {
+                let symbol = Symbol {
+                    binding: SymbolBinding::Local,
+                    ..*object.symbol_table.get(reference.symbol)
+                };
+                object.symbol_table.replace(reference.symbol, symbol);
+            }
+            let data =
+                object
+                    .section_table
+                    .add_section(".data", SectionKind::Data, 8, vec![0; 0x10]);
+            let (loop_symbol, exit_symbol) =
+                (object.references[0].symbol, object.references[1].symbol);
+            for (offset, symbol, typ) in [
+                (0, exit_symbol, RiscVRelocationType::Add32),
+                (0, loop_symbol, RiscVRelocationType::Sub32),
+                (4, exit_symbol, RiscVRelocationType::Set8),
+                (4, loop_symbol, RiscVRelocationType::Sub8),
+                (8, exit_symbol, RiscVRelocationType::Direct64),
+            ] {
+                object.references.push(Relocation {
+                    offset,
+                    symbol,
+                    typ: RelocationType::RiscV(typ),
+                    addend: 0,
+                    section: data,
+                });
+            }
+            object
+        };
+        for shared in [false, true] {
+            let config = LinkConfig {
+                architecture: Architecture::RiscV,
+                pie: !shared,
+                shared,
+                ..config()
+            };
+            let executable = link(vec![LinkInput::Object(object())], &config).unwrap();
+
+            // Check result: only the pointer is filled in at load time
+            let section_table = &executable.section_table;
+            let data = section_table.section_by_name(".data").unwrap();
+            let data = data.data.bytes().unwrap();
+            assert_eq!(data[0..4], 0x1a_u32.to_le_bytes());
+            assert_eq!(data[4], 0x1a);
+            let [relocation] = executable.dynamic_relocations[..] else {
+                panic!("only the pointer is relocated at load time");
+            };
+            assert_eq!(relocation.kind, DynamicRelocationKind::Relative);
+        }
+    }
+
+    #[test]
+    fn shared_object() {
+        let config = LinkConfig {
+            shared: true,
+            soname: Some("libsum.so".to_string()),
+            ..config()
+        };
+
+        // Call `sum` through the PLT, load `array` through the GOT, and point
+        // `array` to a symbol that another module defines
+        let mut object = main_o();
+        object.references[0].typ = RelocationType::X86_64(X86_64RelocationType::Plt32);
+        object.references[1].typ = RelocationType::X86_64(X86_64RelocationType::GotPcRel);
+        object.references[1].addend = -4;
+        let array = object.references[1].symbol;
+        let SymbolValue::Defined(definition) = object.symbol_table.get(array).value else {
+            panic!("`array` is defined");
+        };
+        let external = object.symbol_table.add(Symbol {
+            name: "external",
+            value: SymbolValue::Undefined,
+            binding: SymbolBinding::Global,
+        });
+        object.references.push(Relocation {
+            offset: 0,
+            symbol: external,
+            typ: RelocationType::X86_64(X86_64RelocationType::Direct64),
+            addend: 0,
+            section: definition.section,
+        });
+        let objects = vec![LinkInput::Object(object), LinkInput::Object(sum_o())];
+        let executable = link(objects, &config).unwrap();
+
+        // Check result: every global is exported and preemptible
+        let symbol_table = &executable.symbol_table;
+        let name = |symbol| symbol_table.get(symbol).name;
+        let mut exported = executable
+            .dynamic_symbols
+            .iter()
+            .map(|&symbol| name(symbol))
+            .collect::<Vec<_>>();
+        exported.sort();
+        assert_eq!(exported, ["array", "external", "sum"]);
+        let relocations = executable
+            .dynamic_relocations
+            .iter()
+            .map(|relocation| match relocation.kind {
+                DynamicRelocationKind::Relative => panic!("all symbols are preemptible"),
+                DynamicRelocationKind::Absolute(symbol) => ("R_X86_64_64", name(symbol)),
+                DynamicRelocationKind::GotEntry(symbol) => ("GLOB_DAT", name(symbol)),
+                DynamicRelocationKind::PltEntry(symbol) => ("JUMP_SLOT", name(symbol)),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            relocations,
+            [
+                ("R_X86_64_64", "external"),
+                ("GLOB_DAT", "array"),
+                ("JUMP_SLOT", "sum"),
+            ]
+        );
+
+        // The call goes to the stub, which jumps through `.got.plt`
+        let section_table = &executable.section_table;
+        let address = |name| {
+            let index = section_table
+                .indices()
+                .find(|&index| section_table.section(index).name == name)
+                .unwrap();
+            section_table.address(index)
+        };
+        let (plt, got_plt) = (address(".plt"), address(".got.plt"));
+        let text = section_table.section_by_name(".text").unwrap();
+        let text = text.data.bytes().unwrap();
+        let call = i32::from_le_bytes(text[0xf..0x13].try_into().unwrap());
+        assert_eq!(address(".text") + 0x13 + call as usize, plt + 16);
+        let plt = section_table.section_by_name(".plt").unwrap();
+        let stub = &plt.data.bytes().unwrap()[16..32];
+        let jump = i32::from_le_bytes(stub[2..6].try_into().unwrap());
+        assert_eq!(address(".plt") + 16 + 6 + jump as usize, got_plt + 24);
+    }
+
+    #[test]
+    fn misaligned_branch() {
+        let mut object = jump_o("sum", Some("finish"));
+        object.references[0].offset = 0;
+        object.references[0].addend = 0;
+        object.references[0].typ = RelocationType::Aarch64(Aarch64RelocationType::Call26);
+        let objects = vec![
+            LinkInput::Object(object),
+            LinkInput::Object(jump_o("finish", None)),
+        ];
+
+        // Link
+        let config = LinkConfig {
+            architecture: Architecture::Aarch64,
+            ..config()
+        };
+        let result = link(objects, &config);
+
+        // Check result
+        let Err(diagnostics) = result else {
+            panic!("`finish` is not misaligned");
+        };
+        let [Diagnostic {
+            error:
+                LinkError::RelocationError(RelocationError::Misaligned {
+                    symbol,
+                    value,
+                    alignment,
+                    ..
+                }),
+            ..
+        }] = &diagnostics[..]
+        else {
+            panic!("`finish` is not misaligned");
+        };
+        assert_eq!(symbol, "finish");
+        assert_eq!(*value, 5);
+        assert_eq!(*alignment, 4);
+    }
+
+    #[test]
+    fn origin_names_innermost_symbol() {
+        // `inner` is the second jump of `outer`, and listed first
+        let mut object = InMemoryRelocatableObject {
+            name: "nested.o",
+            section_table: InMemoryLoadableSectionTable::new(),
+            symbol_table: SymbolTable::new(),
+            references: vec![],
+        };
+        let text = object.section_table.add_section(
+            ".text",
+            SectionKind::Text,
+            1,
+            vec![
+                0xe9, 0x00, 0x00, 0x00, 0x00, // jmp missing
+                0xe9, 0x00, 0x00, 0x00, 0x00, // inner: jmp missing
+            ],
+        );
+        for (name, offset, size) in [("inner", 5, 5), ("outer", 0, 10)] {
+            object.symbol_table.add(Symbol {
+                name,
+                value: SymbolValue::Defined(SymbolDefinition {
+                    section: text,
+                    offset,
+                    size,
+                }),
+                binding: SymbolBinding::Global,
+            });
+        }
+        let missing = object.symbol_table.add(Symbol {
+            name: "missing",
+            value: SymbolValue::Undefined,
+            binding: SymbolBinding::Global,
+        });
+        for offset in [1, 6] {
+            object.references.push(Relocation {
+                offset,
+                symbol: missing,
+                typ: RelocationType::X86_64(X86_64RelocationType::Pc32),
+                addend: -4,
+                section: text,
+            });
+        }
+
+        // Link
+        let Err(diagnostics) = link(vec![LinkInput::Object(object)], &config()) else {
+            panic!("`missing` is defined");
+        };
+
+        // Check result
+        let symbols = diagnostics
+            .iter()
+            .map(|diagnostic| match &diagnostic.error {
+                LinkError::RelocationError(RelocationError::SymbolNotDefined {
+                    origin, ..
+                }) => origin.symbol.clone(),
+                error => panic!("unexpected error: {error}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(symbols, [Some("outer".into()), Some("inner".into())]);
+    }
+
+    #[test]
+    fn relocation_out_of_bounds() {
+        // Past the end, and so far that the end overflows
+        for offset in [0x15, usize::MAX - 1] {
+            let mut object = main_o();
+            object.references[0].offset = offset;
+            let objects = vec![LinkInput::Object(object), LinkInput::Object(sum_o())];
+
+            // Link
+            let result = link(objects, &config());
+
+            // Check result
+            assert_eq!(
+                result.err(),
+                Some(vec![LinkError::ValidationError(
+                    ValidationError::RelocationOutOfBounds {
+                        section: ".text".to_string(),
+                        offset,
+                    }
+                )
+                .into()])
+            );
+        }
+    }
+
+    #[test]
+    fn symbol_out_of_bounds() {
+        // Past the end, and so far that the end overflows
+        for size in [12, usize::MAX] {
+            let mut object = main_o();
+            let array = object.symbol_table.iter().nth(1).unwrap().0;
+            let mut symbol = *object.symbol_table.get(array);
+            let SymbolValue::Defined(definition) = &mut symbol.value else {
+                unreachable!();
+            };
+            definition.size = size;
+            object.symbol_table.replace(array, symbol);
+            let objects = vec![LinkInput::Object(object), LinkInput::Object(sum_o())];
+
+            // Link
+            let result = link(objects, &config());
+
+            // Check result
+            assert_eq!(
+                result.err(),
+                Some(vec![LinkError::ValidationError(
+                    ValidationError::SymbolOutOfBounds {
+                        symbol: "array".to_string(),
+                        section: ".data".to_string(),
+                    }
+                )
+                .into()])
+            );
+        }
+    }
+
+    #[test]
+    fn common_symbol_without_alignment() {
+        let objects = vec![LinkInput::Object(common_buffer_o(8, 0))];
+
+        // Link
+        let result = link(objects, &config());
+
+        // Check result
+        assert_eq!(
+            result.err(),
+            Some(vec![LinkError::ValidationError(
+                ValidationError::ZeroAlignment {
+                    symbol: "buffer".to_string(),
+                }
+            )
+            .into()])
+        );
+    }
+
+    #[test]
+    fn overlapping_relocations() {
+        let mut object = main_o();
+        object.references[1].offset = 0xd;
+        let objects = vec![LinkInput::Object(object), LinkInput::Object(sum_o())];
+
+        // Link
+        let result = link(objects, &config());
+
+        // Check result
+        assert_eq!(
+            result.err(),
+            Some(vec![LinkError::ValidationError(
+                ValidationError::OverlappingRelocations {
+                    section: ".text".to_string(),
+                    first: 0xd,
+                    second: 0xf,
+                }
+            )
+            .into()])
+        );
+
+        // `ADD8` may share the field of `ADD64`, but `DIRECT32` may not, even though
+        // it starts after the end of `ADD8`
+        let mut object = compressed_branches_o();
+        let data = object
+            .section_table
+            .add_section(".data", SectionKind::Data, 8, vec![0; 8]);
+        let symbol = object.references[0].symbol;
+        for (offset, typ) in [
+            (0, RiscVRelocationType::Add64),
+            (0, RiscVRelocationType::Add8),
+            (4, RiscVRelocationType::Direct32),
+        ] {
+            object.references.push(Relocation {
+                offset,
+                section: data,
+                typ: RelocationType::RiscV(typ),
+                symbol,
+                addend: 0,
+            });
+        }
+        let config = LinkConfig {
+            architecture: Architecture::RiscV,
+            ..config()
+        };
+        assert_eq!(
+            link(vec![LinkInput::Object(object)], &config).err(),
+            Some(vec![LinkError::ValidationError(
+                ValidationError::OverlappingRelocations {
+                    section: ".data".to_string(),
+                    first: 0,
+                    second: 4,
+                }
+            )
+            .into()])
+        );
+    }
+
+    #[test]
+    fn foreign_relocation() {
+        let objects = vec![LinkInput::Object(main_o()), LinkInput::Object(sum_o())];
+        let config = LinkConfig {
+            architecture: Architecture::RiscV,
+            ..config()
+        };
+        let result = link(objects, &config);
+        assert_eq!(
+            result.err(),
+            Some(vec![LinkError::ValidationError(
+                ValidationError::ForeignRelocation {
+                    section: ".text".to_string(),
+                    offset: 0xa,
+                    architecture: Architecture::X86_64,
+                }
+            )
+            .into()])
+        );
+    }
+
+    #[test]
+    fn compressed_branches() {
+        let objects = vec![LinkInput::Object(compressed_branches_o())];
+
+        // Link
+        let config = LinkConfig {
+            architecture: Architecture::RiscV,
+            ..config()
+        };
+        let section_table = link(objects, &config).unwrap().section_table;
+
+        // Check result
+        let text = section_table.section_by_name(".text").unwrap();
+        assert_eq!(
+            &text.data.bytes().unwrap()[0x8..0xc],
+            &[
+                0x65, 0xdd, // c.beqz a0, loop
+                0x01, 0xa8, // c.j exit
+            ]
+        );
+    }
+
+    #[test]
+    fn conflicting_definitions() {
+        let objects = vec![
+            LinkInput::Object(main_o()),
+            LinkInput::Object(sum_o()),
+            LinkInput::Object(jump_o("sum", None)),
+        ];
+
+        // Link
+        let result = link(objects, &config());
+
+        // Check result
+        assert_eq!(
+            result.err(),
+            Some(vec![LinkError::ResolveError(
+                ResolveError::ConflictSymbols {
+                    symbol: "sum".to_string(),
+                    first: "sum.o".to_string(),
+                    second: "jump.o".to_string(),
+                }
+            )
+            .into()])
+        );
+    }
+
+    #[test]
+    fn errors_grouped_by_symbol() {
+        let objects = ["first", "second", "third", "fourth", "fifth"]
+            .into_iter()
+            .map(|name| LinkInput::Object(jump_o(name, Some("finish"))))
+            .chain([LinkInput::Object(main_o())])
+            .collect();
+
+        // Link
+        let Err(diagnostics) = link(objects, &config()) else {
+            panic!("`finish` is defined");
+        };
+
+        // Every undefined symbol is reported, `finish` only up to the cap
+        let symbols = diagnostics
+            .iter()
+            .map(|diagnostic| match &diagnostic.error {
+                LinkError::RelocationError(RelocationError::SymbolNotDefined {
+                    symbol, ..
+                }) => (symbol.as_str(), diagnostic.more),
+                error => panic!("unexpected error {error}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            symbols,
+            [("finish", 0), ("finish", 0), ("finish", 2), ("sum", 0)]
+        );
+        assert!(diagnostics[2]
+            .to_string()
+            .ends_with("in `third` (referenced 2 more times)"));
+    }
+
+    #[test]
+    fn strong_overrides_weak() {
+        for objects in [
+            vec![
+                LinkInput::Object(weak_sum_o()),
+                LinkInput::Object(main_o()),
+                LinkInput::Object(sum_o()),
+            ],
+            vec![
+                LinkInput::Object(main_o()),
+                LinkInput::Object(sum_o()),
+                LinkInput::Object(weak_sum_o()),
+            ],
+        ] {
+            // Link
+            let executable = link(objects, &config()).unwrap();
+
+            // Check result
+            let sum = executable.symbol_table.get_by_name("sum").unwrap();
+            assert_eq!(sum.binding, SymbolBinding::Global);
+            let SymbolValue::Defined(sum) = sum.value else {
+                panic!("`sum` not defined");
+            };
+            let text = executable.section_table.section_by_name(".text").unwrap();
+            assert_eq!(
+                &text.data.bytes().unwrap()[sum.offset..sum.offset + 2],
+                &[0xb8, 0x00]
+            );
+        }
+    }
+
+    #[test]
+    fn first_weak_wins() {
+        // `sum.o` with a weak `sum`, which starts with `mov eax, 0`
+        let other_weak_sum_o = || {
+            let mut object = sum_o();
+            let (index, sum) = object
+                .symbol_table
+                .iter()
+                .find(|(_, symbol)| symbol.name == "sum")
+                .unwrap();
+            let sum = Symbol {
+                binding: SymbolBinding::Weak,
+                ..*sum
+            };
+            object.symbol_table.replace(index, sum);
+            object
+        };
+        for (objects, first_bytes) in [
+            (
+                vec![
+                    LinkInput::Object(main_o()),
+                    LinkInput::Object(weak_sum_o()),
+                    LinkInput::Object(other_weak_sum_o()),
+                ],
+                [0x31, 0xc0],
+            ),
+            (
+                vec![
+                    LinkInput::Object(main_o()),
+                    LinkInput::Object(other_weak_sum_o()),
+                    LinkInput::Object(weak_sum_o()),
+                ],
+                [0xb8, 0x00],
+            ),
+        ] {
+            // Link
+            let executable = link(objects, &config()).unwrap();
+
+            // Check result
+            let sum = executable.symbol_table.get_by_name("sum").unwrap();
+            assert_eq!(sum.binding, SymbolBinding::Weak);
+            let SymbolValue::Defined(sum) = sum.value else {
+                panic!("`sum` not defined");
+            };
+            let text = executable.section_table.section_by_name(".text").unwrap();
+            assert_eq!(
+                &text.data.bytes().unwrap()[sum.offset..sum.offset + 2],
+                &first_bytes
+            );
+        }
+    }
+
+    #[test]
+    fn undefined_weak_is_zero() {
+        let objects = vec![LinkInput::Object(weak_hook_o())];
+
+        // Link
+        let config = LinkConfig {
+            entry: EntryPoint::Address(0),
+            ..Default::default()
+        };
+        let section_table = link(objects, &config).unwrap().section_table;
+
+        // Check result
+        assert_eq!(
+            section_table
+                .section_by_name(".text")
+                .unwrap()
+                .data
+                .bytes()
+                .unwrap(),
+            vec![
+                0xbf, 0x00, 0x00, 0x00, 0x00, // mov edi, hook
+            ]
+        );
+    }
+
+    #[test]
+    fn local_symbols_stay_in_object() {
+        let objects = vec![
+            LinkInput::Object(local_cleanup_o(1)),
+            LinkInput::Object(local_cleanup_o(2)),
+        ];
+
+        // Link
+        let executable = link(objects, &config()).unwrap();
+
+        // Check result
+        assert_eq!(executable.symbol_table.get_by_name("cleanup"), None);
+        assert_eq!(executable.symbol_table.len(), 2);
+        let text = executable.section_table.section_by_name(".text").unwrap();
+        assert_eq!(
+            text.data.bytes().unwrap(),
+            vec![
+                0xe8, 0x01, 0x00, 0x00, 0x00, // call +1 <cleanup>
+                0xc3, // ret
+                0xb0, 0x01, // cleanup: mov al, 1
+                0xc3, // ret
+                0xe8, 0x01, 0x00, 0x00, 0x00, // call +1 <cleanup>
+                0xc3, // ret
+                0xb0, 0x02, // cleanup: mov al, 2
+                0xc3, // ret
+            ]
+        );
+    }
+
+    #[test]
+    fn common_symbols_merge() {
+        let objects = vec![
+            LinkInput::Object(common_buffer_o(8, 4)),
+            LinkInput::Object(common_buffer_o(16, 8)),
+        ];
+
+        // Link
+        let executable = link(objects, &config()).unwrap();
+
+        // Check result
+        let bss = executable.section_table.section_by_name(".bss").unwrap();
+        assert_eq!(bss.data, SectionData::ZeroFill(16));
+        assert_eq!(bss.alignment, 8);
+        let buffer = executable.symbol_table.get_by_name("buffer").unwrap();
+        let SymbolValue::Defined(buffer) = buffer.value else {
+            panic!("`buffer` not allocated");
+        };
+        assert_eq!(buffer.size, 16);
+        let text = executable.section_table.section_by_name(".text").unwrap();
+        assert_eq!(
+            text.data.bytes().unwrap(),
+            vec![
+                0xbf, 0x10, 0x00, 0x00, 0x00, // mov edi, buffer
+                0xbf, 0x10, 0x00, 0x00, 0x00, // mov edi, buffer
+            ]
+        );
+    }
+
+    #[test]
+    fn absolute_symbols() {
+        // The value of `answer` is stored as is
+        let data = |executable: InMemoryExecutableObject| {
+            let data = executable.section_table.section_by_name(".data").unwrap();
+            data.data.bytes().unwrap().to_vec()
+        };
+        let executable = link(vec![LinkInput::Object(absolute_o(false))], &config()).unwrap();
+        assert_eq!(data(executable), 42u64.to_le_bytes());
+
+        // and needs no dynamic relocation wherever the image is loaded
+        let config = LinkConfig {
+            pie: true,
+            ..config()
+        };
+        let executable = link(vec![LinkInput::Object(absolute_o(false))], &config).unwrap();
+        assert_eq!(executable.dynamic_relocations, []);
+        assert_eq!(data(executable), 42u64.to_le_bytes());
+
+        // but cannot be reached relative to the code
+        let Err(diagnostics) = link(vec![LinkInput::Object(absolute_o(true))], &config) else {
+            panic!("`answer` is not relative to the image");
+        };
+        assert_eq!(
+            diagnostics,
+            [LinkError::PositionDependentRelocation {
+                symbol: "answer".to_string(),
+                origin: RelocationOrigin {
+                    object: "absolute.o".into(),
+                    section: ".text".into(),
+                    offset: 3,
+                    symbol: None,
+                },
+            }
+            .into()]
+        );
+    }
+
+    #[test]
+    fn definition_overrides_common() {
+        let mut defined_buffer_o = common_buffer_o(0, 1);
+        let data_section = defined_buffer_o.section_table.add_section(
+            ".data",
+            SectionKind::Data,
+            1,
+            vec![0xff; 4],
+        );
+        let (index, buffer) = defined_buffer_o.symbol_table.iter().next().unwrap();
+        let buffer = Symbol {
+            value: SymbolValue::Defined(SymbolDefinition {
+                section: data_section,
+                offset: 0,
+                size: 4,
+            }),
+            ..*buffer
+        };
+        defined_buffer_o.symbol_table.replace(index, buffer);
+        let objects = vec![
+            LinkInput::Object(common_buffer_o(8, 4)),
+            LinkInput::Object(defined_buffer_o),
+        ];
+
+        // Link
+        let executable = link(objects, &config()).unwrap();
+
+        // Check result
+        assert_eq!(executable.section_table.section_by_name(".bss"), None);
+        let text = executable.section_table.section_by_name(".text").unwrap();
+        assert_eq!(
+            text.data.bytes().unwrap(),
+            vec![
+                0xbf, 0x0a, 0x00, 0x00, 0x00, // mov edi, buffer
+                0xbf, 0x0a, 0x00, 0x00, 0x00, // mov edi, buffer
+            ]
+        );
+    }
+
+    #[test]
+    fn archive_members_on_demand() {
+        let inputs = vec![
+            LinkInput::Object(main_o()),
+            LinkInput::Archive(archive(vec![sum_o(), counter_o()])),
+        ];
+
+        // Link
+        let executable = link(inputs, &config()).unwrap();
+
+        // Check result
+        assert!(executable.symbol_table.get_by_name("sum").is_some());
+        assert!(executable.symbol_table.get_by_name("counter").is_none());
+        assert_eq!(executable.section_table.section_by_name(".bss"), None);
+    }
+
+    #[test]
+    fn group_resolves_cycles() {
+        // `sum` needs `helper` from the second archive, which needs `finish` from the first
+        let archives = || {
+            vec![
+                archive(vec![jump_o("sum", Some("helper")), jump_o("finish", None)]),
+                archive(vec![jump_o("helper", Some("finish"))]),
+            ]
+        };
+
+        // Link archives in order
+        let inputs = [LinkInput::Object(main_o())]
+            .into_iter()
+            .chain(archives().into_iter().map(LinkInput::Archive))
+            .collect();
+        let Err(diagnostics) = link(inputs, &config()) else {
+            panic!("`finish` is defined");
+        };
+        assert_eq!(
+            diagnostics,
+            vec![
+                LinkError::RelocationError(RelocationError::SymbolNotDefined {
+                    symbol: "finish".to_string(),
+                    origin: RelocationOrigin {
+                        object: "jump.o".into(),
+                        section: ".text".into(),
+                        offset: 0x1,
+                        symbol: Some("helper".into()),
+                    },
+                })
+                .into()
+            ]
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error: Relocation error: Undefined reference to `finish` in jump.o:(.text+0x1) in `helper`"
+        );
+
+        // Link archives as a group
+        let inputs = vec![LinkInput::Object(main_o()), LinkInput::Group(archives())];
+        let executable = link(inputs, &config()).unwrap();
+        assert!(executable.symbol_table.get_by_name("finish").is_some());
+    }
+
+    #[test]
+    fn gc_sections() {
+        let objects = vec![
+            LinkInput::Object(jump_o("start", Some("sum"))),
+            LinkInput::Object(main_o()),
+            LinkInput::Object(sum_o()),
+            LinkInput::Object(jump_o("unused", Some("missing"))),
+        ];
+
+        // Link
+        let config = LinkConfig {
+            entry: EntryPoint::Symbol("start".to_string()),
+            gc_sections: true,
+            ..config()
+        };
+        let executable = link(objects, &config).unwrap();
+
+        // Check result: `missing` is only referenced by a removed section
+        assert_eq!(
+            executable
+                .removed_sections
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "removing unused section '.text' in file 'main.o'",
+                "removing unused section '.data' in file 'main.o'",
+                "removing unused section '.text' in file 'jump.o'",
+            ]
+        );
+        assert_eq!(executable.section_table.section_by_name(".data"), None);
+        assert!(executable.symbol_table.get_by_name("array").is_none());
+        assert!(executable.symbol_table.get_by_name("unused").is_none());
+        let text = executable.section_table.section_by_name(".text").unwrap();
+        assert_eq!(text.data.len(), 5 + 0x1b);
+    }
+
+    #[test]
+    fn gc_keeps_exported_symbols_and_kept_sections() {
+        let mut unused = jump_o("unused", Some("missing"));
+        unused
+            .section_table
+            .section_mut(unused.references[0].section)
+            .keep = true;
+        let objects = vec![
+            LinkInput::Object(main_o()),
+            LinkInput::Object(sum_o()),
+            LinkInput::Object(unused),
+        ];
+
+        // Link
+        let config = LinkConfig {
+            gc_sections: true,
+            exported_symbols: vec!["array".to_string()],
+            ..config()
+        };
+        let result = link(objects, &config);
+
+        // Check result: the kept section still needs `missing`
+        let Err(diagnostics) = result else {
+            panic!("`missing` is defined");
+        };
+        let [Diagnostic {
+            error: LinkError::RelocationError(RelocationError::SymbolNotDefined { symbol, .. }),
+            ..
+        }] = &diagnostics[..]
+        else {
+            panic!("`missing` is defined");
+        };
+        assert_eq!(symbol, "missing");
+
+        // Without it, only the text of `main.o` and `sum.o` is removed
+        let objects = vec![LinkInput::Object(main_o()), LinkInput::Object(sum_o())];
+        let executable = link(objects, &config).unwrap();
+        assert_eq!(
+            executable.removed_sections,
+            [
+                RemovedSection {
+                    object: "main.o".to_string(),
+                    section: ".text".to_string(),
+                },
+                RemovedSection {
+                    object: "sum.o".to_string(),
+                    section: ".text".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn gc_keeps_unwind_tables() {
+        // A CIE, then an FDE with an LSDA for each of `unused` and `start`
+        let mut unwind = InMemoryRelocatableObject {
+            name: "unwind.o",
+            section_table: InMemoryLoadableSectionTable::new(),
+            symbol_table: SymbolTable::new(),
+            references: vec![],
+        };
+        let fde = |cie_distance: u8| {
+            [
+                [0x14, 0x00, 0x00, 0x00],         // length
+                [cie_distance, 0x00, 0x00, 0x00], // CIE pointer
+                [0x00; 4],                        // pc_begin
+                [0x01, 0x00, 0x00, 0x00],         // pc_range
+                [0x04, 0x00, 0x00, 0x00],         // augmentation length, LSDA...
+                [0x00; 4],                        // ...LSDA, padding
+            ]
+            .concat()
+        };
+        let eh_frame = [
+            vec![
+                0x0c, 0x00, 0x00, 0x00, // length
+                0x00, 0x00, 0x00, 0x00, // CIE id
+                0x01, b'z', b'L', b'R', 0x00, // version, augmentation
+                0x01, 0x78, 0x10, // alignment factors, return address register
+            ],
+            fde(0x14),
+            fde(0x2c),
+        ]
+        .concat();
+        let eh_frame =
+            unwind
+                .section_table
+                .add_section(".eh_frame", SectionKind::ReadOnlyData, 8, eh_frame);
+        unwind.section_table.section_mut(eh_frame).unwind = true;
+        for (fde, function) in [(0x10, "unused"), (0x28, "start")] {
+            let function = unwind.symbol_table.add(Symbol {
+                name: function,
+                value: SymbolValue::Undefined,
+                binding: SymbolBinding::Global,
+            });
+            let lsda_name = match fde {
+                0x10 => ".gcc_except_table.unused",
+                _ => ".gcc_except_table.start",
+            };
+            let lsda = unwind.section_table.add_section(
+                lsda_name,
+                SectionKind::ReadOnlyData,
+                1,
+                vec![0xff; 4],
+            );
+            let lsda = unwind.symbol_table.add(Symbol {
+                name: lsda_name,
+                value: SymbolValue::Defined(SymbolDefinition {
+                    section: lsda,
+                    offset: 0,
+                    size: 0,
+                }),
+                binding: SymbolBinding::Local,
+            });
+            for (offset, symbol) in [(fde + 8, function), (fde + 0x11, lsda)] {
+                unwind.references.push(Relocation {
+                    offset,
+                    symbol,
+                    typ: RelocationType::X86_64(X86_64RelocationType::Pc32),
+                    addend: 0,
+                    section: eh_frame,
+                });
+            }
+        }
+        let objects = vec![
+            LinkInput::Object(jump_o("start", None)),
+            LinkInput::Object(jump_o("unused", None)),
+            LinkInput::Object(unwind),
+        ];
+
+        // Link
+        let config = LinkConfig {
+            entry: EntryPoint::Symbol("start".to_string()),
+            gc_sections: true,
+            ..config()
+        };
+        let executable = link(objects, &config).unwrap();
+
+        // Check result: the table keeps the LSDA of `start`, but does not keep
+        // `unused` alive, and loses its FDE
+        assert_eq!(
+            executable.removed_sections,
+            [
+                RemovedSection {
+                    object: "jump.o".to_string(),
+                    section: ".text".to_string(),
+                },
+                RemovedSection {
+                    object: "unwind.o".to_string(),
+                    section: ".gcc_except_table.unused".to_string(),
+                },
+            ]
+        );
+        let section_table = &executable.section_table;
+        let address = |name| {
+            let index = section_table
+                .indices()
+                .find(|&index| section_table.section(index).name == name)
+                .unwrap();
+            section_table.address(index)
+        };
+        let eh_frame = section_table.section_by_name(".eh_frame").unwrap();
+        let bytes = eh_frame.data.bytes().unwrap();
+        assert_eq!(bytes.len(), 0x28);
+        let word =
+            |offset: usize| i32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        let pointer = |offset: usize| {
+            (address(".eh_frame") + offset).wrapping_add_signed(word(offset) as isize)
+        };
+        assert_eq!(word(0x14), 0x14);
+        assert_eq!(pointer(0x18), address(".text"));
+        assert_eq!(pointer(0x21), address(".gcc_except_table.start"));
+    }
+
+    #[test]
+    fn identical_code_folding() {
+        let objects = vec![
+            LinkInput::Object(jump_o("a", Some("x"))),
+            LinkInput::Object(jump_o("b", Some("y"))),
+            LinkInput::Object(jump_o("x", None)),
+            LinkInput::Object(jump_o("y", None)),
+        ];
+
+        // Link
+        let config = LinkConfig {
+            icf: Icf::All,
+            ..config()
+        };
+        let executable = link(objects, &config).unwrap();
+
+        // Check result: `x` and `y` fold, so `a` and `b` do too
+        let text = executable.section_table.section_by_name(".text").unwrap();
+        assert_eq!(text.data.len(), 5 + 1);
+        let address = |name| {
+            let symbol = executable.symbol_table.get_by_name(name).unwrap();
+            let SymbolValue::Defined(definition) = symbol.value else {
+                panic!("`{name}` is defined");
+            };
+            definition.offset
+        };
+        assert_eq!(address("a"), address("b"));
+        assert_eq!(address("x"), address("y"));
+        assert_eq!(address("x"), 5);
+    }
+
+    #[test]
+    fn safe_identical_code_folding() {
+        let call_o = |name, callee| {
+            let mut object = jump_o(name, Some(callee));
+            object.references[0].typ = RelocationType::X86_64(X86_64RelocationType::Plt32);
+            object
+        };
+        let config = LinkConfig {
+            icf: Icf::Safe,
+            ..config()
+        };
+
+        // Sections that are only called fold
+        let objects = vec![
+            LinkInput::Object(call_o("a", "x")),
+            LinkInput::Object(call_o("b", "y")),
+            LinkInput::Object(jump_o("x", None)),
+            LinkInput::Object(jump_o("y", None)),
+        ];
+        let executable = link(objects, &config).unwrap();
+        let text = executable.section_table.section_by_name(".text").unwrap();
+        assert_eq!(text.data.len(), 5 + 1);
+
+        // Taking the address of `y` keeps it, and so `b`, apart
+        let objects = vec![
+            LinkInput::Object(call_o("a", "x")),
+            LinkInput::Object(jump_o("b", Some("y"))),
+            LinkInput::Object(jump_o("x", None)),
+            LinkInput::Object(jump_o("y", None)),
+        ];
+        let executable = link(objects, &config).unwrap();
+        let text = executable.section_table.section_by_name(".text").unwrap();
+        assert_eq!(text.data.len(), 5 + 5 + 1 + 1);
+    }
+
+    #[test]
+    fn identical_code_folding_keeps_output_sections_apart() {
+        // Identical read-only data in `.rodata`, an LSDA and an unwind table, twice
+        let object = || {
+            let mut object = InMemoryRelocatableObject {
+                name: "tables.o",
+                section_table: InMemoryLoadableSectionTable::new(),
+                symbol_table: SymbolTable::new(),
+                references: vec![],
+            };
+            for name in [".rodata.a", ".gcc_except_table.a", ".eh_frame"] {
+                let index = object.section_table.add_section(
+                    name,
+                    SectionKind::ReadOnlyData,
+                    1,
+                    vec![0; 4],
+                );
+                object.section_table.section_mut(index).unwind = name == ".eh_frame";
+            }
+            object
+        };
+        let objects = vec![LinkInput::Object(object()), LinkInput::Object(object())];
+
+        // Link
+        let config = LinkConfig {
+            icf: Icf::All,
+            ..config()
+        };
+        let executable = link(objects, &config).unwrap();
+
+        // Check result: only the sections of the same output section fold, and unwind
+        // tables do not
+        let sizes = executable
+            .section_table
+            .sections()
+            .map(|section| (section.name.as_str(), section.data.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sizes,
+            [(".rodata", 4), (".gcc_except_table.a", 4), (".eh_frame", 8),]
+        );
+    }
+
+    #[test]
+    fn entry_symbol() {
+        let objects = vec![LinkInput::Object(main_o()), LinkInput::Object(sum_o())];
+
+        // Link
+        let mut config = config();
+        config.entry = EntryPoint::Symbol("sum".to_string());
+        let executable = link(objects, &config).unwrap();
+
+        // Check result
+        assert_eq!(executable.entry, 0x18);
+    }
+
+    #[test]
+    fn missing_entry() {
+        let objects = vec![LinkInput::Object(sum_o())];
+
+        // Link
+        let mut config = config();
+        config.entry = EntryPoint::Symbol("_start".to_string());
+        let result = link(objects, &config);
+
+        // Check result
+        assert_eq!(
+            result.err(),
+            Some(vec![LinkError::EntryNotDefined {
+                symbol: "_start".to_string()
+            }
+            .into()])
+        );
+    }
+
+    fn main_o() -> InMemoryRelocatableObject<'static> {
+        let mut section_table = InMemoryLoadableSectionTable::new();
+        let mut symbol_table = SymbolTable::new();
+        let mut references = Vec::new();
+
+        // Add loadable sections
+        let text_section = section_table.add_section(
+            ".text",
+            SectionKind::Text,
+            1,
+            vec![
+                0x48, 0x83, 0xec, 0x08, // sub rsp, 8
+                0xbe, 0x02, 0x00, 0x00, 0x00, // mov esi, 2
+                0xbf, 0x00, 0x00, 0x00, 0x00, // mov edi, array
+                0xe8, 0x00, 0x00, 0x00, 0x00, // call sum
+                0x48, 0x83, 0xc4, 0x08, // add rsp, 8
+                0xc3, // ret
+            ],
+        );
+        let data_section = section_table.add_section(
+            ".data",
+            SectionKind::Data,
+            1,
+            vec![
+                0x01, 0x00, 0x00, 0x00, // array: .int 1
+                0x02, 0x00, 0x00, 0x00, // .int 2
+            ],
+        );
+
+        // Add symbols
+        let sum_symbol = symbol_table.add(Symbol {
+            name: "sum",
+            value: SymbolValue::Undefined,
+            binding: SymbolBinding::Global,
+        });
+        let array_symbol = symbol_table.add(Symbol {
+            name: "array",
+            value: SymbolValue::Defined(SymbolDefinition {
+                section: data_section,
+                offset: 0,
+                size: 8,
+            }),
+            binding: SymbolBinding::Global,
+        });
+
+        // Add references
+        references.push(Relocation {
+            offset: 0xf,
+            symbol: sum_symbol,
+            typ: RelocationType::X86_64(X86_64RelocationType::Pc32),
+            addend: -4,
+            section: text_section,
+        });
+        references.push(Relocation {
+            offset: 0xa,
+            symbol: array_symbol,
+            typ: RelocationType::X86_64(X86_64RelocationType::Direct32),
+            addend: 0,
+            section: text_section,
+        });
+
+        InMemoryRelocatableObject {
+            name: "main.o",
+            section_table,
+            symbol_table,
+            references,
+        }
+    }
+
+    fn sum_o() -> InMemoryRelocatableObject<'static> {
+        aligned_sum_o(1)
+    }
+
+    fn aligned_sum_o(alignment: usize) -> InMemoryRelocatableObject<'static> {
+        let mut section_table = InMemoryLoadableSectionTable::new();
+        let mut symbol_table = SymbolTable::new();
+        let references = Vec::new();
+
+        // Add loadable sections
+        let text_section = section_table.add_section(
+            ".text",
+            SectionKind::Text,
+            alignment,
+            vec![
+                0xb8, 0x00, 0x00, 0x00, 0x00, // mov eax, 0
+                0xba, 0x00, 0x00, 0x00, 0x00, // mov edx, 0
+                0xeb, 0x09, // jmp +9
+                0x48, 0x63, 0xca, // movsxd rcx, edx
+                0x03, 0x04, 0x8f, // add eax, [rdi + rcx * 4]
+                0x83, 0xc2, 0x01, // add edx, 1
+                0x39, 0xf2, // cmp edx, esi
+                0x7c, 0xf3, // jl -13
+                0xf3, 0xc3, // rep ret
+            ],
+        );
+
+        // Add symbols
+        let _sum_symbol = symbol_table.add(Symbol {
+            name: "sum",
+            value: SymbolValue::Defined(SymbolDefinition {
+                section: text_section,
+                offset: 0,
+                size: 0,
+            }),
+            binding: SymbolBinding::Global,
+        });
+
+        // Add references
+        // None
+
+        InMemoryRelocatableObject {
+            name: "sum.o",
+            section_table,
+            symbol_table,
+            references,
+        }
+    }
+
+    /// Defines `counter` in `.bss`, listed before `.text`.
+    fn counter_o() -> InMemoryRelocatableObject<'static> {
+        let mut section_table = InMemoryLoadableSectionTable::new();
+        let mut symbol_table = SymbolTable::new();
+        let mut references = Vec::new();
+
+        // Add loadable sections
+        let bss_section = section_table.add_zero_fill_section(".bss", SectionKind::Data, 1, 16);
+        let text_section = section_table.add_section(
+            ".text",
+            SectionKind::Text,
+            1,
+            vec![
+                0x83, 0x04, 0x25, 0x00, 0x00, 0x00, 0x00, 0x01, // add dword ptr [counter], 1
+                0xc3, // ret
+            ],
+        );
+
+        // Add symbols
+        let counter_symbol = symbol_table.add(Symbol {
+            name: "counter",
+            value: SymbolValue::Defined(SymbolDefinition {
+                section: bss_section,
+                offset: 0,
+                size: 16,
+            }),
+            binding: SymbolBinding::Global,
+        });
+
+        // Add references
+        references.push(Relocation {
+            offset: 0x3,
+            symbol: counter_symbol,
+            typ: RelocationType::X86_64(X86_64RelocationType::Direct32),
+            addend: 0,
+            section: text_section,
+        });
+
+        InMemoryRelocatableObject {
+            name: "counter.o",
+            section_table,
+            symbol_table,
+            references,
+        }
+    }
+
+    /// `sum_o` with its code in `.text.sum`, preceded by an unrelated `.rodata.str` section.
+    fn sum_with_rodata_o() -> InMemoryRelocatableObject<'static> {
+        let mut section_table = InMemoryLoadableSectionTable::new();
+        let mut symbol_table = SymbolTable::new();
+        let references = Vec::new();
+
+        // Add loadable sections
+        let _rodata_section = section_table.add_section(
+            ".rodata.str",
+            SectionKind::ReadOnlyData,
+            1,
+            b"sum\0".to_vec(),
+        );
+        let text_section = section_table.add_section(
+            ".text.sum",
+            SectionKind::Text,
+            1,
+            vec![
+                0xb8, 0x00, 0x00, 0x00, 0x00, // mov eax, 0
+                0xba, 0x00, 0x00, 0x00, 0x00, // mov edx, 0
+                0xeb, 0x09, // jmp +9
+                0x48, 0x63, 0xca, // movsxd rcx, edx
+                0x03, 0x04, 0x8f, // add eax, [rdi + rcx * 4]
+                0x83, 0xc2, 0x01, // add edx, 1
+                0x39, 0xf2, // cmp edx, esi
+                0x7c, 0xf3, // jl -13
+                0xf3, 0xc3, // rep ret
+            ],
+        );
+
+        // Add symbols
+        let _sum_symbol = symbol_table.add(Symbol {
+            name: "sum",
+            value: SymbolValue::Defined(SymbolDefinition {
+                section: text_section,
+                offset: 0,
+                size: 0,
+            }),
+            binding: SymbolBinding::Global,
+        });
+
+        // Add references
+        // None
+
+        InMemoryRelocatableObject {
+            name: "sum_with_rodata.o",
+            section_table,
+            symbol_table,
+            references,
+        }
+    }
+
+    /// A weak fallback definition of `sum` that always returns 0.
+    fn weak_sum_o() -> InMemoryRelocatableObject<'static> {
+        let mut section_table = InMemoryLoadableSectionTable::new();
+        let mut symbol_table = SymbolTable::new();
+        let references = Vec::new();
+
+        // Add loadable sections
+        let text_section = section_table.add_section(
+            ".text",
+            SectionKind::Text,
+            1,
+            vec![
+                0x31, 0xc0, // xor eax, eax
+                0xc3, // ret
+            ],
+        );
+
+        // Add symbols
+        let _sum_symbol = symbol_table.add(Symbol {
+            name: "sum",
+            value: SymbolValue::Defined(SymbolDefinition {
+                section: text_section,
+                offset: 0,
+                size: 3,
+            }),
+            binding: SymbolBinding::Weak,
+        });
+
+        // Add references
+        // None
+
+        InMemoryRelocatableObject {
+            name: "weak_sum.o",
+            section_table,
+            symbol_table,
+            references,
+        }
+    }
+
+    /// References `hook`, which may be left undefined.
+    fn weak_hook_o() -> InMemoryRelocatableObject<'static> {
+        let mut section_table = InMemoryLoadableSectionTable::new();
+        let mut symbol_table = SymbolTable::new();
+        let mut references = Vec::new();
+
+        // Add loadable sections
+        let text_section = section_table.add_section(
+            ".text",
+            SectionKind::Text,
+            1,
+            vec![
+                0xbf, 0x00, 0x00, 0x00, 0x00, // mov edi, hook
+            ],
+        );
+
+        // Add symbols
+        let hook_symbol = symbol_table.add(Symbol {
+            name: "hook",
+            value: SymbolValue::Undefined,
+            binding: SymbolBinding::Weak,
+        });
+
+        // Add references
+        references.push(Relocation {
+            offset: 0x1,
+            symbol: hook_symbol,
+            typ: RelocationType::X86_64(X86_64RelocationType::Direct32),
+            addend: 0,
+            section: text_section,
+        });
+
+        InMemoryRelocatableObject {
+            name: "weak_hook.o",
+            section_table,
+            symbol_table,
+            references,
+        }
+    }
+
+    /// Calls its own `static` helper `cleanup`, which returns `value`.
+    fn local_cleanup_o(value: u8) -> InMemoryRelocatableObject<'static> {
+        let mut section_table = InMemoryLoadableSectionTable::new();
+        let mut symbol_table = SymbolTable::new();
+        let mut references = Vec::new();
+
+        // Add loadable sections
+        let text_section = section_table.add_section(
+            ".text",
+            SectionKind::Text,
+            1,
+            vec![
+                0xe8, 0x00, 0x00, 0x00, 0x00, // call cleanup
+                0xc3, // ret
+                0xb0, value, // cleanup: mov al, value
+                0xc3,  // ret
+            ],
+        );
+
+        // Add symbols
+        let cleanup_symbol = symbol_table.add(Symbol {
+            name: "cleanup",
+            value: SymbolValue::Defined(SymbolDefinition {
+                section: text_section,
+                offset: 6,
+                size: 3,
+            }),
+            binding: SymbolBinding::Local,
+        });
+
+        // Add references
+        references.push(Relocation {
+            offset: 0x1,
+            symbol: cleanup_symbol,
+            typ: RelocationType::X86_64(X86_64RelocationType::Pc32),
+            addend: -4,
+            section: text_section,
+        });
+
+        InMemoryRelocatableObject {
+            name: "local_cleanup.o",
+            section_table,
+            symbol_table,
+            references,
+        }
+    }
+
+    /// References `buffer`, declared as a common symbol.
+    fn common_buffer_o(size: usize, alignment: usize) -> InMemoryRelocatableObject<'static> {
+        let mut section_table = InMemoryLoadableSectionTable::new();
+        let mut symbol_table = SymbolTable::new();
+        let mut references = Vec::new();
+
+        // Add loadable sections
+        let text_section = section_table.add_section(
+            ".text",
+            SectionKind::Text,
+            1,
+            vec![
+                0xbf, 0x00, 0x00, 0x00, 0x00, // mov edi, buffer
+            ],
+        );
+
+        // Add symbols
+        let buffer_symbol = symbol_table.add(Symbol {
+            name: "buffer",
+            value: SymbolValue::Common { size, alignment },
+            binding: SymbolBinding::Global,
+        });
+
+        // Add references
+        references.push(Relocation {
+            offset: 0x1,
+            symbol: buffer_symbol,
+            typ: RelocationType::X86_64(X86_64RelocationType::Direct32),
+            addend: 0,
+            section: text_section,
+        });
+
+        InMemoryRelocatableObject {
+            name: "common_buffer.o",
+            section_table,
+            symbol_table,
+            references,
+        }
+    }
+
+    /// Stores the absolute symbol `answer` in `.data`, and loads its address relative to
+    /// the code if `pc_relative`.
+    fn absolute_o(pc_relative: bool) -> InMemoryRelocatableObject<'static> {
+        let mut section_table = InMemoryLoadableSectionTable::new();
+        let mut symbol_table = SymbolTable::new();
+        let mut references = Vec::new();
+
+        // Add loadable sections
+        let text_section = section_table.add_section(
+            ".text",
+            SectionKind::Text,
+            1,
+            vec![
+                0x48, 0x8d, 0x05, 0x00, 0x00, 0x00, 0x00, // lea rax, [rip + answer]
+            ],
+        );
+        let data_section = section_table.add_section(".data", SectionKind::Data, 8, vec![0; 8]);
+
+        // Add symbols
+        let answer_symbol = symbol_table.add(Symbol {
+            name: "answer",
+            value: SymbolValue::Absolute(42),
+            binding: SymbolBinding::Global,
+        });
+
+        // Add references
+        references.push(Relocation {
+            offset: 0x0,
+            symbol: answer_symbol,
+            typ: RelocationType::X86_64(X86_64RelocationType::Direct64),
+            addend: 0,
+            section: data_section,
+        });
+        if pc_relative {
+            references.push(Relocation {
+                offset: 0x3,
+                symbol: answer_symbol,
+                typ: RelocationType::X86_64(X86_64RelocationType::Pc32),
+                addend: -4,
+                section: text_section,
+            });
+        }
+
+        InMemoryRelocatableObject {
+            name: "absolute.o",
+            section_table,
+            symbol_table,
+            references,
+        }
+    }
+
+    /// Indexes the symbols defined by `objects`.
+    fn archive(objects: Vec<InMemoryRelocatableObject<'static>>) -> InMemoryArchive<'static> {
+        let mut symbol_index = HashMap::new();
+        for (index, object) in objects.iter().enumerate() {
+            for (_, symbol) in object.symbol_table.iter() {
+                if let SymbolValue::Defined(_) = symbol.value {
+                    symbol_index.insert(symbol.name, index);
+                }
+            }
+        }
+        InMemoryArchive {
+            members: objects
+                .into_iter()
+                .map(InMemoryArchiveMember::parsed)
+                .collect(),
+            symbol_index,
+        }
+    }
+
+    /// Defines `name`, which jumps to `callee` or returns.
+    fn jump_o(
+        name: &'static str,
+        callee: Option<&'static str>,
+    ) -> InMemoryRelocatableObject<'static> {
+        let mut section_table = InMemoryLoadableSectionTable::new();
+        let mut symbol_table = SymbolTable::new();
+        let mut references = Vec::new();
+
+        // Add loadable sections
+        let text = match callee {
+            Some(_) => vec![
+                0xe9, 0x00, 0x00, 0x00, 0x00, // jmp callee
+            ],
+            None => vec![
+                0xc3, // ret
+            ],
+        };
+        let text_len = text.len();
+        let text_section = section_table.add_section(".text", SectionKind::Text, 1, text);
+
+        // Add symbols
+        symbol_table.add(Symbol {
+            name,
+            value: SymbolValue::Defined(SymbolDefinition {
+                section: text_section,
+                offset: 0,
+                size: text_len,
+            }),
+            binding: SymbolBinding::Global,
+        });
+        if let Some(callee) = callee {
+            let callee_symbol = symbol_table.add(Symbol {
+                name: callee,
+                value: SymbolValue::Undefined,
+                binding: SymbolBinding::Global,
+            });
+
+            // Add references
+            references.push(Relocation {
+                offset: 0x1,
+                symbol: callee_symbol,
+                typ: RelocationType::X86_64(X86_64RelocationType::Pc32),
+                addend: -4,
+                section: text_section,
+            });
+        }
+
+        InMemoryRelocatableObject {
+            name: "jump.o",
+            section_table,
+            symbol_table,
+            references,
+        }
+    }
+
+    /// Branches back to `loop` and forward to `exit` with RISC-V compressed instructions.
+    fn compressed_branches_o() -> InMemoryRelocatableObject<'static> {
+        let mut section_table = InMemoryLoadableSectionTable::new();
+        let mut symbol_table = SymbolTable::new();
+        let mut references = Vec::new();
+
+        // Add loadable sections
+        let mut text = [0x01, 0x00].repeat(4); // loop: c.nop
+        text.extend([
+            0x01, 0xc1, // c.beqz a0, loop
+            0x01, 0xa0, // c.j exit
+        ]);
+        text.extend([0x01, 0x00].repeat(8)); // c.nop, exit: c.nop
+        let text_section = section_table.add_section(".text", SectionKind::Text, 2, text);
+
+        // Add symbols
+        let loop_symbol = symbol_table.add(Symbol {
+            name: "loop",
+            value: SymbolValue::Defined(SymbolDefinition {
+                section: text_section,
+                offset: 0,
+                size: 0,
+            }),
+            binding: SymbolBinding::Global,
+        });
+        let exit_symbol = symbol_table.add(Symbol {
+            name: "exit",
+            value: SymbolValue::Defined(SymbolDefinition {
+                section: text_section,
+                offset: 0x1a,
+                size: 0,
+            }),
+            binding: SymbolBinding::Global,
+        });
+
+        // Add references
+        references.push(Relocation {
+            offset: 0x8,
+            symbol: loop_symbol,
+            typ: RelocationType::RiscV(RiscVRelocationType::RvcBranch),
+            addend: 0,
+            section: text_section,
+        });
+        references.push(Relocation {
+            offset: 0xa,
+            symbol: exit_symbol,
+            typ: RelocationType::RiscV(RiscVRelocationType::RvcJump),
+            addend: 0,
+            section: text_section,
+        });
 
         InMemoryRelocatableObject {
+            name: "compressed_branches.o",
             section_table,
             symbol_table,
             references,
@@ -0,0 +1,259 @@
+use std::fmt::Write;
+
+use crate::core::models::{LoadableSectionTable, SymbolValue};
+
+use super::models::{Contribution, InMemoryExecutableObject};
+
+/// Where everything ended up in an executable, like `ld -Map`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkMap<'a> {
+    /// Output sections in address order.
+    pub sections: Vec<MapSection<'a>>,
+    /// Defined symbols in address order.
+    pub symbols: Vec<MapSymbol<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapSection<'a> {
+    pub name: &'a str,
+    pub address: usize,
+    pub size: usize,
+    pub contributions: &'a [Contribution],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapSymbol<'a> {
+    pub name: &'a str,
+    pub address: usize,
+    pub size: usize,
+    /// The output section that contains the symbol.
+    pub section: &'a str,
+    /// The object that defines the symbol.
+    pub object: &'a str,
+}
+
+impl<'a> LinkMap<'a> {
+    pub fn new(executable: &'a InMemoryExecutableObject) -> Self {
+        let section_table = &executable.section_table;
+        let sections = section_table
+            .indices()
+            .map(|index| {
+                let section = section_table.section(index);
+                MapSection {
+                    name: &section.name,
+                    address: section_table.address(index),
+                    size: section.data.len(),
+                    contributions: &section.contributions,
+                }
+            })
+            .collect();
+
+        let symbol_table = &executable.symbol_table;
+        let mut symbols = symbol_table
+            .iter()
+            .filter_map(|(index, symbol)| {
+                let SymbolValue::Defined(definition) = symbol.value else {
+                    return None;
+                };
+                Some(MapSymbol {
+                    name: symbol.name,
+                    address: section_table.address(definition.section) + definition.offset,
+                    size: definition.size,
+                    section: &section_table.section(definition.section).name,
+                    object: symbol_table.object(index),
+                })
+            })
+            .collect::<Vec<_>>();
+        symbols.sort_by_key(|symbol| (symbol.address, symbol.name));
+
+        Self { sections, symbols }
+    }
+
+    /// Formats the map for people to read.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        text.push_str("Output sections\n\n");
+        for section in &self.sections {
+            let _ = writeln!(
+                text,
+                "{:<32} {:#018x} {:#10x}",
+                section.name, section.address, section.size
+            );
+            for contribution in section.contributions {
+                let address = section.address + contribution.offset;
+                if contribution.padding > 0 {
+                    let _ = writeln!(
+                        text,
+                        " {:<31} {:#018x} {:#10x}",
+                        "*fill*",
+                        address - contribution.padding,
+                        contribution.padding
+                    );
+                }
+                let _ = writeln!(
+                    text,
+                    " {:<31} {:#018x} {:#10x}",
+                    format!("{}({})", contribution.object, contribution.section),
+                    address,
+                    contribution.size
+                );
+            }
+        }
+
+        text.push_str("\nSymbols\n\n");
+        for symbol in &self.symbols {
+            let _ = writeln!(
+                text,
+                "{:#018x} {:#10x} {:<24} {}({})",
+                symbol.address, symbol.size, symbol.name, symbol.object, symbol.section
+            );
+        }
+        text
+    }
+
+    /// Formats the map as JSON, for tools to ingest.
+    pub fn to_json(&self) -> String {
+        let sections = self
+            .sections
+            .iter()
+            .map(|section| {
+                let contributions = section
+                    .contributions
+                    .iter()
+                    .map(|contribution| {
+                        format!(
+                            r#"{{"object":{},"section":{},"offset":{},"size":{},"padding":{}}}"#,
+                            json_string(&contribution.object),
+                            json_string(&contribution.section),
+                            contribution.offset,
+                            contribution.size,
+                            contribution.padding
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                format!(
+                    r#"{{"name":{},"address":{},"size":{},"contributions":[{}]}}"#,
+                    json_string(section.name),
+                    section.address,
+                    section.size,
+                    contributions.join(",")
+                )
+            })
+            .collect::<Vec<_>>();
+        let symbols = self
+            .symbols
+            .iter()
+            .map(|symbol| {
+                format!(
+                    r#"{{"name":{},"address":{},"size":{},"section":{},"object":{}}}"#,
+                    json_string(symbol.name),
+                    symbol.address,
+                    symbol.size,
+                    json_string(symbol.section),
+                    json_string(symbol.object)
+                )
+            })
+            .collect::<Vec<_>>();
+        format!(
+            r#"{{"sections":[{}],"symbols":[{}]}}"#,
+            sections.join(","),
+            symbols.join(",")
+        )
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if c < ' ' => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        elf::read_relocatable_object,
+        in_memory::{link, models::LinkConfig, LinkInput},
+    };
+
+    use super::*;
+
+    const MAIN_O: &[u8] = include_bytes!("../../fixtures/main.o");
+    const SUM_O: &[u8] = include_bytes!("../../fixtures/sum.o");
+    const COUNTER_O: &[u8] = include_bytes!("../../fixtures/counter.o");
+    const START_O: &[u8] = include_bytes!("../../fixtures/start.o");
+
+    fn executable() -> InMemoryExecutableObject<'static> {
+        let objects = vec![
+            LinkInput::Object(read_relocatable_object("start.o", START_O).unwrap()),
+            LinkInput::Object(read_relocatable_object("main.o", MAIN_O).unwrap()),
+            LinkInput::Object(read_relocatable_object("sum.o", SUM_O).unwrap()),
+            LinkInput::Object(read_relocatable_object("counter.o", COUNTER_O).unwrap()),
+        ];
+        link(objects, &LinkConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn text_map() {
+        let executable = executable();
+        let map = LinkMap::new(&executable);
+
+        assert_eq!(
+            map.to_text(),
+            "\
+Output sections
+
+.text                            0x0000000000400000       0x4a
+ start.o(.text)                  0x0000000000400000        0xe
+ main.o(.text)                   0x000000000040000e       0x18
+ sum.o(.text)                    0x0000000000400026       0x1b
+ counter.o(.text)                0x0000000000400041        0x9
+.data                            0x000000000040104a        0x8
+ main.o(.data)                   0x000000000040104a        0x8
+.bss                             0x0000000000401052       0x10
+ counter.o(.bss)                 0x0000000000401052       0x10
+
+Symbols
+
+0x0000000000400000        0x0 _start                   start.o(.text)
+0x000000000040000e        0x0 main                     main.o(.text)
+0x0000000000400026        0x0 sum                      sum.o(.text)
+0x0000000000400041        0x0 bump                     counter.o(.text)
+0x000000000040104a        0x0 array                    main.o(.data)
+0x0000000000401052        0x0 counter                  counter.o(.bss)
+"
+        );
+    }
+
+    #[test]
+    fn json_map() {
+        let executable = executable();
+        let map = LinkMap::new(&executable);
+
+        let json = map.to_json();
+        assert!(json.starts_with(concat!(
+            r#"{"sections":[{"name":".text","address":4194304,"size":74,"contributions":["#,
+            r#"{"object":"start.o","section":".text","offset":0,"size":14,"padding":0},"#,
+        )));
+        assert!(json.ends_with(concat!(
+            r#"{"name":"counter","address":4198482,"size":0,"section":".bss","object":"counter.o"}"#,
+            "]}",
+        )));
+    }
+
+    #[test]
+    fn escape_json_strings() {
+        assert_eq!(json_string("a\"b\\c\n\u{1}"), r#""a\"b\\c\n\u0001""#);
+    }
+}
@@ -1,4 +1,10 @@
 pub use link::*;
+pub use map::*;
 
+mod gc;
+mod icf;
 mod link;
+mod map;
 pub mod models;
+mod plt;
+mod unwind;
@@ -0,0 +1,30 @@
+use std::collections::HashMap;
+
+use super::*;
+
+/// A static library (`.a`) whose members are only linked when they define a
+/// symbol that is still undefined.
+pub struct InMemoryArchive<'name> {
+    pub members: Vec<InMemoryArchiveMember<'name>>,
+    /// Member indices by the global symbols they define.
+    pub symbol_index: HashMap<&'name str, usize>,
+}
+
+/// Parses an archive member, or describes why it is invalid.
+pub type ParseMember<'name> =
+    Box<dyn FnOnce() -> Result<InMemoryRelocatableObject<'name>, String> + 'name>;
+
+pub struct InMemoryArchiveMember<'name> {
+    pub name: &'name str,
+    /// Called only if the member is linked.
+    pub parse: ParseMember<'name>,
+}
+impl<'name> InMemoryArchiveMember<'name> {
+    /// A member that is already parsed.
+    pub fn parsed(object: InMemoryRelocatableObject<'name>) -> Self {
+        Self {
+            name: object.name,
+            parse: Box::new(move || Ok(object)),
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use crate::core::models::Architecture;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkConfig {
+    /// The address of the first output section, or of the file headers in front of
+    /// it in position-independent output.
+    pub image_base: usize,
+    /// Output sections placed at fixed addresses, by name (like `--section-start`).
+    ///
+    /// Other sections follow the section placed before them.
+    pub section_starts: HashMap<String, usize>,
+    /// If set, an output section whose kind differs from the one before it starts on
+    /// a new page, so that segments with different permissions never share a page.
+    pub page_size: Option<usize>,
+    /// Where the program starts executing (like `--entry`).
+    pub entry: EntryPoint,
+    /// The instruction set of the output (like `-m`), which every relocation must be
+    /// for.
+    pub architecture: Architecture,
+    /// The byte order of relocated data; instruction fields are always little-endian.
+    pub endianness: Endianness,
+    /// Discards input sections that cannot be reached from the entry point, the
+    /// exported symbols or sections marked to keep (like `--gc-sections`).
+    pub gc_sections: bool,
+    /// Symbols that must be kept even if nothing refers to them, besides those a
+    /// shared object exports.
+    pub exported_symbols: Vec<String>,
+    /// Which identical read-only sections are folded into one (like `--icf`).
+    pub icf: Icf,
+    /// Produces a position-independent executable (like `-pie`): absolute addresses
+    /// in writable data are left to relative relocations applied at load time.
+    pub pie: bool,
+    /// Produces a shared object (like `-shared`): global symbols are exported, or
+    /// imported if undefined, and may be preempted by the loader, and the entry point
+    /// is optional.
+    pub shared: bool,
+    /// The name that executables linked against the shared object record as needed
+    /// (like `-soname`).
+    pub soname: Option<String>,
+}
+impl Default for LinkConfig {
+    fn default() -> Self {
+        Self {
+            image_base: 0x400000,
+            section_starts: HashMap::new(),
+            page_size: Some(0x1000),
+            entry: EntryPoint::Symbol("_start".to_string()),
+            architecture: Architecture::X86_64,
+            endianness: Endianness::Little,
+            gc_sections: false,
+            exported_symbols: vec![],
+            icf: Icf::None,
+            pie: false,
+            shared: false,
+            soname: None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Icf {
+    None,
+    /// Only folds sections whose address is never taken, so that distinct functions
+    /// keep distinct addresses.
+    Safe,
+    All,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryPoint {
+    /// The address of a symbol, which must be defined.
+    Symbol(String),
+    Address(usize),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+impl Endianness {
+    pub fn read(&self, field: &[u8]) -> usize {
+        let mut bytes = [0; size_of::<usize>()];
+        let width = field.len();
+        match self {
+            Endianness::Little => {
+                bytes[..width].copy_from_slice(field);
+                usize::from_le_bytes(bytes)
+            }
+            Endianness::Big => {
+                let len = bytes.len();
+                bytes[len - width..].copy_from_slice(field);
+                usize::from_be_bytes(bytes)
+            }
+        }
+    }
+    /// Writes the low `field.len()` bytes of `value` to `field`.
+    pub fn write(&self, field: &mut [u8], value: usize) {
+        let width = field.len();
+        match self {
+            Endianness::Little => field.copy_from_slice(&value.to_le_bytes()[..width]),
+            Endianness::Big => {
+                let bytes = value.to_be_bytes();
+                field.copy_from_slice(&bytes[bytes.len() - width..])
+            }
+        }
+    }
+}
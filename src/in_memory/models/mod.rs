@@ -1,5 +1,9 @@
+pub use archive::*;
+pub use config::*;
 pub use object::*;
 pub use section::*;
 
+mod archive;
+mod config;
 mod object;
 mod section;
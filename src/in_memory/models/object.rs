@@ -1,9 +1,226 @@
-use crate::core::models::*;
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::{core::models::*, in_memory::Diagnostic};
 
 use super::*;
 
 pub struct InMemoryRelocatableObject<'name> {
+    /// Identifies the object in diagnostics (e.g. its file path or archive member name).
+    pub name: &'name str,
     pub section_table: InMemoryLoadableSectionTable,
     pub symbol_table: SymbolTable<'name, InMemorySectionIndex>,
     pub references: Vec<Relocation<InMemorySectionIndex>>,
 }
+impl InMemoryRelocatableObject<'_> {
+    /// Checks that every symbol and relocation stays within its section, that
+    /// tentative definitions have an alignment, and that relocations are for
+    /// `architecture`.
+    pub fn validate(&self, architecture: Architecture) -> Result<(), ValidationError> {
+        for (_, symbol) in self.symbol_table.iter() {
+            let definition = match symbol.value {
+                SymbolValue::Defined(definition) => definition,
+                SymbolValue::Common { alignment: 0, .. } => {
+                    return Err(ValidationError::ZeroAlignment {
+                        symbol: symbol.name.to_string(),
+                    });
+                }
+                _ => continue,
+            };
+            let section = self.section(definition.section)?;
+            let end = definition.offset.checked_add(definition.size);
+            if end.is_none_or(|end| end > section.data.len()) {
+                return Err(ValidationError::SymbolOutOfBounds {
+                    symbol: symbol.name.to_string(),
+                    section: section.name.clone(),
+                });
+            }
+        }
+
+        let mut references = self.references.iter().collect::<Vec<_>>();
+        references.sort_by_key(|reference| (reference.section, reference.offset));
+        // The relocation whose field reaches furthest into its section so far
+        let mut furthest: Option<&Relocation<InMemorySectionIndex>> = None;
+        for reference in references {
+            let section = self.section(reference.section)?;
+            let field = reference.typ.field();
+            if !self.symbol_table.contains(reference.symbol) {
+                return Err(ValidationError::UnknownSymbol {
+                    section: section.name.clone(),
+                    offset: reference.offset,
+                });
+            }
+            if reference.typ.architecture() != architecture {
+                return Err(ValidationError::ForeignRelocation {
+                    section: section.name.clone(),
+                    offset: reference.offset,
+                    architecture: reference.typ.architecture(),
+                });
+            }
+            let end = reference.offset.checked_add(field.width);
+            let Some(end) = end.filter(|&end| end <= section.data.len()) else {
+                return Err(ValidationError::RelocationOutOfBounds {
+                    section: section.name.clone(),
+                    offset: reference.offset,
+                });
+            };
+
+            // Only relocations that accumulate into the same bytes may share them
+            // (e.g. RISC-V `ADD32`/`SUB32` pairs), or follow one that sets the same
+            // field (e.g. RISC-V `SET8`/`SUB8` pairs)
+            let furthest_end = furthest
+                .filter(|furthest| furthest.section == reference.section)
+                .map(|furthest| furthest.offset + furthest.typ.field().width);
+            if let Some(furthest) =
+                furthest.filter(|_| furthest_end.is_some_and(|end| end > reference.offset))
+            {
+                let sets_same_field = furthest.offset == reference.offset
+                    && furthest.typ.field().width == field.width;
+                if !(is_accumulating(reference) && (is_accumulating(furthest) || sets_same_field)) {
+                    return Err(ValidationError::OverlappingRelocations {
+                        section: section.name.clone(),
+                        first: furthest.offset,
+                        second: reference.offset,
+                    });
+                }
+            }
+            if furthest_end.is_none_or(|furthest_end| end > furthest_end) {
+                furthest = Some(reference);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the sections for which `keep` returns false, along with the symbols
+    /// defined in them and the relocations that patch them.
+    pub fn retain_sections(&mut self, keep: impl FnMut(InMemorySectionIndex) -> bool) {
+        let sections = self.section_table.retain(keep);
+
+        let mut symbol_table = SymbolTable::new();
+        let mut symbols = HashMap::new();
+        for (index, symbol) in std::mem::take(&mut self.symbol_table) {
+            let value = match symbol.value {
+                SymbolValue::Defined(definition) => {
+                    let Some(&section) = sections.get(&definition.section) else {
+                        continue;
+                    };
+                    SymbolValue::Defined(SymbolDefinition {
+                        section,
+                        ..definition
+                    })
+                }
+                value => value,
+            };
+            symbols.insert(index, symbol_table.add(Symbol { value, ..symbol }));
+        }
+        self.symbol_table = symbol_table;
+
+        self.references = std::mem::take(&mut self.references)
+            .into_iter()
+            .filter_map(|reference| {
+                Some(Relocation {
+                    section: *sections.get(&reference.section)?,
+                    symbol: *symbols.get(&reference.symbol)?,
+                    ..reference
+                })
+            })
+            .collect();
+    }
+
+    /// Empties a section that is identical to another one and drops its relocations;
+    /// its symbols are redirected to the other section when merging.
+    pub fn fold_section(&mut self, index: InMemorySectionIndex) {
+        let section = self.section_table.section_mut(index);
+        section.data = SectionData::Bytes(vec![]);
+        section.alignment = 1;
+        self.references
+            .retain(|reference| reference.section != index);
+    }
+
+    fn section(&self, index: InMemorySectionIndex) -> Result<&InMemorySection, ValidationError> {
+        self.section_table
+            .get(index)
+            .ok_or(ValidationError::UnknownSection { section: index })
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum ValidationError {
+    #[error("Unknown section {section:?}")]
+    UnknownSection { section: InMemorySectionIndex },
+    #[error("Symbol `{symbol}` extends past the end of section `{section}`")]
+    SymbolOutOfBounds { symbol: String, section: String },
+    #[error("Tentative definition of `{symbol}` has an alignment of 0")]
+    ZeroAlignment { symbol: String },
+    #[error("Relocation at `{section}`+{offset:#x} references an unknown symbol")]
+    UnknownSymbol { section: String, offset: usize },
+    #[error("Relocation at `{section}`+{offset:#x} is for {architecture:?}, not the output")]
+    ForeignRelocation {
+        section: String,
+        offset: usize,
+        architecture: Architecture,
+    },
+    #[error("Relocation at `{section}`+{offset:#x} extends past the end of the section")]
+    RelocationOutOfBounds { section: String, offset: usize },
+    #[error("Relocations at `{section}`+{first:#x} and `{section}`+{second:#x} overlap")]
+    OverlappingRelocations {
+        section: String,
+        first: usize,
+        second: usize,
+    },
+}
+
+fn is_accumulating(reference: &Relocation<InMemorySectionIndex>) -> bool {
+    matches!(
+        reference.typ.field().layout,
+        FieldLayout::Add | FieldLayout::Subtract
+    )
+}
+
+/// The result of linking: relocated sections and the symbols defined in them.
+pub struct InMemoryExecutableObject<'name> {
+    pub section_table: InMemoryLoadableSectionTable,
+    pub symbol_table: ResolvingSymbolTable<'name, InMemorySectionIndex>,
+    /// The address where execution starts.
+    pub entry: usize,
+    /// The input sections discarded by garbage collection.
+    pub removed_sections: Vec<RemovedSection>,
+    pub architecture: Architecture,
+    /// The byte order of data.
+    pub endianness: Endianness,
+    /// Whether the executable can be loaded at any address.
+    pub pie: bool,
+    /// Whether the output is a shared object rather than an executable.
+    pub shared: bool,
+    /// The name recorded in `DT_SONAME`, for a shared object.
+    pub soname: Option<String>,
+    /// The symbols that a shared object exports or imports.
+    pub dynamic_symbols: Vec<SymbolIndex>,
+    /// The words the loader must fill in, in address order.
+    pub dynamic_relocations: Vec<DynamicRelocation>,
+    /// The warnings of the link.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// An address-sized word that the loader fills in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DynamicRelocation {
+    /// The link-time address of the word.
+    pub address: usize,
+    pub kind: DynamicRelocationKind,
+    pub addend: usize,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DynamicRelocationKind {
+    /// The load address plus the addend (like `R_X86_64_RELATIVE`).
+    Relative,
+    /// The address of the symbol plus the addend (like `R_X86_64_64`).
+    Absolute(SymbolIndex),
+    /// The address of the symbol, in a GOT entry (like `R_X86_64_GLOB_DAT`).
+    GotEntry(SymbolIndex),
+    /// The address of the symbol, in a `.got.plt` entry that the PLT jumps through
+    /// (like `R_X86_64_JUMP_SLOT`).
+    PltEntry(SymbolIndex),
+}
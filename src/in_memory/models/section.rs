@@ -1,49 +1,418 @@
+use std::{collections::HashMap, fmt};
+
+use thiserror::Error;
+
 use crate::core::models::*;
 
+use super::LinkConfig;
+
+/// Input sections whose names equal one of these, or extend one of these with
+/// a `.` suffix (e.g. `.text.main`), are merged into the output section of that name.
+const OUTPUT_SECTION_NAMES: [&str; 6] = [
+    ".text",
+    ".rodata",
+    ".data",
+    ".bss",
+    ".init_array",
+    ".fini_array",
+];
+
+/// The room that position-independent output leaves at the image base for the file
+/// headers, which its loader reads from memory. This also keeps every symbol off
+/// address 0, which loaders take to mean undefined.
+const HEADERS_SIZE: usize = 0x1000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InMemorySection {
+    pub name: String,
+    pub kind: SectionKind,
+    /// The required alignment of the section's address, in bytes.
+    pub alignment: usize,
+    pub data: SectionData,
+    /// Whether garbage collection must keep the section, even if nothing refers to it
+    /// (like `KEEP` in linker scripts).
+    pub keep: bool,
+    /// Whether the section holds unwind tables (e.g. `.eh_frame`), which garbage
+    /// collection keeps without keeping the code they describe.
+    pub unwind: bool,
+    /// The input sections merged into this section, in order.
+    pub contributions: Vec<Contribution>,
+}
+
+/// An input section merged into an output section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Contribution {
+    pub object: String,
+    /// The name of the input section.
+    pub section: String,
+    /// The offset within the output section.
+    pub offset: usize,
+    pub size: usize,
+    /// The bytes inserted before the contribution to align it.
+    pub padding: usize,
+}
+
+/// An input section discarded by garbage collection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemovedSection {
+    pub object: String,
+    pub section: String,
+}
+impl fmt::Display for RemovedSection {
+    /// Formats the section like `--print-gc-sections`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "removing unused section '{}' in file '{}'",
+            self.section, self.object
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SectionData {
+    Bytes(Vec<u8>),
+    /// Occupies address space but has no contents in the file (e.g. `.bss`).
+    ZeroFill(usize),
+}
+impl SectionData {
+    pub fn len(&self) -> usize {
+        match self {
+            SectionData::Bytes(bytes) => bytes.len(),
+            SectionData::ZeroFill(len) => *len,
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn is_zero_fill(&self) -> bool {
+        matches!(self, SectionData::ZeroFill(_))
+    }
+    pub fn bytes(&self) -> Option<&[u8]> {
+        match self {
+            SectionData::Bytes(bytes) => Some(bytes),
+            SectionData::ZeroFill(_) => None,
+        }
+    }
+    pub fn bytes_mut(&mut self) -> Option<&mut Vec<u8>> {
+        match self {
+            SectionData::Bytes(bytes) => Some(bytes),
+            SectionData::ZeroFill(_) => None,
+        }
+    }
+    fn pad_to(&mut self, len: usize) {
+        match self {
+            SectionData::Bytes(bytes) => bytes.resize(len, 0),
+            SectionData::ZeroFill(old_len) => *old_len = len,
+        }
+    }
+    /// Appends `other`, materializing zeros only if bytes follow them.
+    fn extend(&mut self, other: SectionData) {
+        match (&mut *self, other) {
+            (SectionData::Bytes(bytes), SectionData::Bytes(other)) => bytes.extend(other),
+            (SectionData::Bytes(bytes), SectionData::ZeroFill(len)) => {
+                bytes.resize(bytes.len() + len, 0)
+            }
+            (SectionData::ZeroFill(len), SectionData::ZeroFill(other)) => *len += other,
+            (SectionData::ZeroFill(len), SectionData::Bytes(other)) => {
+                let mut bytes = vec![0; *len];
+                bytes.extend(other);
+                *self = SectionData::Bytes(bytes);
+            }
+        }
+    }
+    fn empty_like(&self) -> Self {
+        match self {
+            SectionData::Bytes(_) => SectionData::Bytes(vec![]),
+            SectionData::ZeroFill(_) => SectionData::ZeroFill(0),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SectionKind {
+    /// Executable code.
+    Text,
+    ReadOnlyData,
+    /// Writable data.
+    Data,
+}
+
 pub struct InMemoryLoadableSectionTable {
-    sections: Vec<Vec<u8>>,
+    sections: Vec<InMemorySection>,
+    /// Section addresses by index, set by `assign_addresses`.
+    addresses: Vec<usize>,
 }
 impl LoadableSectionTable<InMemorySectionIndex> for InMemoryLoadableSectionTable {
     fn len(&self, index: InMemorySectionIndex) -> usize {
         match self.sections.get(index.0) {
-            Some(section) => section.len(),
+            Some(section) => section.data.len(),
             None => 0,
         }
     }
     fn address(&self, index: InMemorySectionIndex) -> usize {
-        let mut sum = 0;
-        for i in 0..index.0 {
-            sum += self.sections[i].len();
-        }
-        sum
+        self.addresses.get(index.0).copied().unwrap_or(0)
     }
 }
 impl InMemoryLoadableSectionTable {
     pub fn new() -> Self {
-        Self { sections: vec![] }
+        Self {
+            sections: vec![],
+            addresses: vec![],
+        }
+    }
+    pub fn add_section(
+        &mut self,
+        name: &str,
+        kind: SectionKind,
+        alignment: usize,
+        data: Vec<u8>,
+    ) -> InMemorySectionIndex {
+        self.push(name, kind, alignment, SectionData::Bytes(data))
     }
-    pub fn add_section(&mut self, section: Vec<u8>) -> InMemorySectionIndex {
+    pub fn add_zero_fill_section(
+        &mut self,
+        name: &str,
+        kind: SectionKind,
+        alignment: usize,
+        len: usize,
+    ) -> InMemorySectionIndex {
+        self.push(name, kind, alignment, SectionData::ZeroFill(len))
+    }
+    fn push(
+        &mut self,
+        name: &str,
+        kind: SectionKind,
+        alignment: usize,
+        data: SectionData,
+    ) -> InMemorySectionIndex {
         let index = self.sections.len();
-        self.sections.push(section);
+        self.sections.push(InMemorySection {
+            name: name.to_string(),
+            kind,
+            alignment: alignment.max(1),
+            data,
+            keep: false,
+            unwind: false,
+            contributions: vec![],
+        });
         InMemorySectionIndex(index)
     }
-    pub fn merge(&mut self, other: Self) {
-        for (i, section) in other.sections.into_iter().enumerate() {
-            if i < self.sections.len() {
-                self.sections[i].extend(section);
-            } else {
-                self.sections.push(section);
+    /// Appends each section of `other`, from `object`, to the output section it is
+    /// grouped into by name.
+    ///
+    /// Each contribution is padded to start at a multiple of its alignment.
+    pub fn merge(&mut self, object: &str, other: Self) -> InMemorySectionMapping {
+        let mut placements = Vec::with_capacity(other.sections.len());
+        for section in other.sections.into_iter() {
+            let name = output_section_name(&section.name);
+            let index = match self.sections.iter().position(|s| s.name == name) {
+                Some(index) => index,
+                None => {
+                    self.push(name, section.kind, 1, section.data.empty_like());
+                    self.sections.len() - 1
+                }
+            };
+            let output = &mut self.sections[index];
+            let offset = align_up(output.data.len(), section.alignment);
+            if !section.data.is_empty() {
+                output.contributions.push(Contribution {
+                    object: object.to_string(),
+                    section: section.name.clone(),
+                    offset,
+                    size: section.data.len(),
+                    padding: offset - output.data.len(),
+                });
             }
+            output.data.pad_to(offset);
+            output.alignment = output.alignment.max(section.alignment);
+            placements.push((
+                SectionPlacement {
+                    section: InMemorySectionIndex(index),
+                    offset,
+                },
+                section.name,
+            ));
+            output.data.extend(section.data);
         }
+        InMemorySectionMapping(placements)
     }
-    pub fn sections(&self) -> impl Iterator<Item = &Vec<u8>> {
+    /// Removes the sections for which `keep` returns false, and returns the new
+    /// index of every remaining section by its old index.
+    pub fn retain(
+        &mut self,
+        mut keep: impl FnMut(InMemorySectionIndex) -> bool,
+    ) -> HashMap<InMemorySectionIndex, InMemorySectionIndex> {
+        let mut indices = HashMap::new();
+        let mut sections = vec![];
+        let mut addresses = vec![];
+        for (index, section) in std::mem::take(&mut self.sections).into_iter().enumerate() {
+            if !keep(InMemorySectionIndex(index)) {
+                continue;
+            }
+            indices.insert(
+                InMemorySectionIndex(index),
+                InMemorySectionIndex(sections.len()),
+            );
+            sections.push(section);
+            addresses.extend(self.addresses.get(index));
+        }
+        self.sections = sections;
+        self.addresses = addresses;
+        indices
+    }
+    /// Assigns an address to every section, starting from the image base.
+    ///
+    /// Zero-fill sections are placed after every section with contents.
+    pub fn assign_addresses(&mut self, config: &LinkConfig) -> Result<(), LayoutError> {
+        let mut addresses = vec![0; self.sections.len()];
+        let mut cursor = match config.pie || config.shared {
+            true => config.image_base + HEADERS_SIZE,
+            false => config.image_base,
+        };
+        let mut previous_kind = None;
+        for index in self.layout_order() {
+            let section = &self.sections[index.0];
+            let address = match config.section_starts.get(&section.name) {
+                Some(&start) => {
+                    if start % section.alignment != 0 {
+                        return Err(LayoutError::MisalignedStart {
+                            section: section.name.clone(),
+                            address: start,
+                            alignment: section.alignment,
+                        });
+                    }
+                    start
+                }
+                None => {
+                    // Move to the next page while keeping the offset within the page,
+                    // so the file does not need to be padded
+                    if let (Some(page_size), Some(previous_kind)) =
+                        (config.page_size, previous_kind)
+                    {
+                        if previous_kind != section.kind {
+                            cursor = align_up(cursor, page_size) + cursor % page_size;
+                        }
+                    }
+                    align_up(cursor, section.alignment)
+                }
+            };
+            addresses[index.0] = address;
+            cursor = address + section.data.len();
+            previous_kind = Some(section.kind);
+        }
+
+        // Check that no two non-empty sections overlap
+        let mut placed = (0..self.sections.len())
+            .filter(|&i| !self.sections[i].data.is_empty())
+            .collect::<Vec<_>>();
+        placed.sort_by_key(|&i| addresses[i]);
+        for pair in placed.windows(2) {
+            let (first, second) = (pair[0], pair[1]);
+            if addresses[first] + self.sections[first].data.len() > addresses[second] {
+                return Err(LayoutError::Overlap {
+                    first: self.sections[first].name.clone(),
+                    second: self.sections[second].name.clone(),
+                });
+            }
+        }
+
+        self.addresses = addresses;
+        Ok(())
+    }
+    pub fn sections(&self) -> impl Iterator<Item = &InMemorySection> {
         self.sections.iter()
     }
-    pub fn section_mut(&mut self, index: InMemorySectionIndex) -> &mut Vec<u8> {
+    /// Returns the sections with their indices, in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = (InMemorySectionIndex, &InMemorySection)> {
+        self.sections
+            .iter()
+            .enumerate()
+            .map(|(index, section)| (InMemorySectionIndex(index), section))
+    }
+    /// Returns the section indices in address order.
+    pub fn indices(&self) -> impl Iterator<Item = InMemorySectionIndex> {
+        let mut indices = self.layout_order().collect::<Vec<_>>();
+        indices.sort_by_key(|&index| self.address(index));
+        indices.into_iter()
+    }
+    /// Sections with contents, then zero-fill sections.
+    fn layout_order(&self) -> impl Iterator<Item = InMemorySectionIndex> + '_ {
+        let is_zero_fill = |i: &usize| self.sections[*i].data.is_zero_fill();
+        let with_contents = (0..self.sections.len()).filter(move |i| !is_zero_fill(i));
+        let zero_fill = (0..self.sections.len()).filter(is_zero_fill);
+        with_contents.chain(zero_fill).map(InMemorySectionIndex)
+    }
+    pub fn get(&self, index: InMemorySectionIndex) -> Option<&InMemorySection> {
+        self.sections.get(index.0)
+    }
+    pub fn section(&self, index: InMemorySectionIndex) -> &InMemorySection {
+        &self.sections[index.0]
+    }
+    pub fn section_by_name(&self, name: &str) -> Option<&InMemorySection> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+    pub fn section_mut(&mut self, index: InMemorySectionIndex) -> &mut InMemorySection {
         &mut self.sections[index.0]
     }
 }
+impl Default for InMemoryLoadableSectionTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum LayoutError {
+    #[error("Sections `{first}` and `{second}` overlap")]
+    Overlap { first: String, second: String },
+    #[error("Section `{section}` at {address:#x} is not aligned to {alignment} bytes")]
+    MisalignedStart {
+        section: String,
+        address: usize,
+        alignment: usize,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct InMemorySectionIndex(usize);
 impl SectionIndex for InMemorySectionIndex {}
+
+/// Maps the section indices of a merged object to their placement in the output.
+pub struct InMemorySectionMapping(Vec<(SectionPlacement<InMemorySectionIndex>, String)>);
+impl InMemorySectionMapping {
+    /// Places an input section where another one was placed.
+    pub fn redirect(
+        &mut self,
+        input: InMemorySectionIndex,
+        placement: SectionPlacement<InMemorySectionIndex>,
+    ) {
+        self.0[input.0].0 = placement;
+    }
+}
+impl SectionMapping<InMemorySectionIndex> for InMemorySectionMapping {
+    fn placement(
+        &self,
+        input: InMemorySectionIndex,
+    ) -> Option<SectionPlacement<InMemorySectionIndex>> {
+        self.0.get(input.0).map(|(placement, _)| *placement)
+    }
+    fn name(&self, input: InMemorySectionIndex) -> Option<&str> {
+        self.0.get(input.0).map(|(_, name)| name.as_str())
+    }
+}
+
+fn align_up(value: usize, alignment: usize) -> usize {
+    value.div_ceil(alignment) * alignment
+}
+
+/// The output section that an input section of this name is merged into.
+pub(crate) fn output_section_name(name: &str) -> &str {
+    OUTPUT_SECTION_NAMES
+        .into_iter()
+        .find(|output| match name.strip_prefix(*output) {
+            Some(suffix) => suffix.is_empty() || suffix.starts_with('.'),
+            None => false,
+        })
+        .unwrap_or(name)
+}
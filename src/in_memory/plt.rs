@@ -0,0 +1,163 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use crate::core::models::{
+    LoadableSectionTable, RelocationType, ResolvingRelocation, ResolvingSymbolTable,
+    SectionMapping, SectionPlacement, Symbol, SymbolBinding, SymbolDefinition, SymbolIndex,
+    SymbolValue,
+};
+
+use super::models::{
+    DynamicRelocation, DynamicRelocationKind, InMemoryLoadableSectionTable, InMemorySectionIndex,
+    SectionKind,
+};
+
+/// Stands in for the object that contributes the PLT and `.got.plt`.
+const PLT_OBJECT: &str = "<plt>";
+
+/// The size of the header and of each entry of `.plt`.
+const PLT_ENTRY_SIZE: usize = 16;
+
+/// The entries of `.got.plt` reserved for the loader.
+const RESERVED_ENTRIES: usize = 3;
+
+const GOT_PLT_ENTRY_SIZE: usize = 8;
+
+/// The x86-64 procedure linkage table: a stub for each preemptible symbol that is
+/// called, which jumps through its `.got.plt` entry. Entries first point back into
+/// the stub, which calls the loader through the header to bind the symbol lazily.
+pub struct ProcedureLinkageTable {
+    plt: SectionPlacement<InMemorySectionIndex>,
+    got_plt: SectionPlacement<InMemorySectionIndex>,
+    /// The symbols with a stub, in order.
+    symbols: Vec<SymbolIndex>,
+}
+impl ProcedureLinkageTable {
+    /// Allocates a stub for each preemptible symbol that an x86-64 branch calls, and
+    /// retargets those branches to a local symbol labeling the stub.
+    pub fn allocate<'name>(
+        section_table: &mut InMemoryLoadableSectionTable,
+        symbol_table: &mut ResolvingSymbolTable<'name, InMemorySectionIndex>,
+        references: &mut [ResolvingRelocation<InMemorySectionIndex>],
+        is_preemptible: impl Fn(SymbolIndex) -> bool,
+    ) -> Option<Self> {
+        let calls = |reference: &ResolvingRelocation<InMemorySectionIndex>| {
+            matches!(reference.typ, RelocationType::X86_64(_))
+                && reference.typ.is_branch()
+                && is_preemptible(reference.symbol)
+        };
+        let mut seen = HashSet::new();
+        let symbols = references
+            .iter()
+            .filter(|reference| calls(reference))
+            .map(|reference| reference.symbol)
+            .filter(|symbol| seen.insert(*symbol))
+            .collect::<Vec<_>>();
+        if symbols.is_empty() {
+            return None;
+        }
+
+        // Merge the tables like any other input sections; they are filled once
+        // addresses are known
+        let mut plt_section_table = InMemoryLoadableSectionTable::new();
+        let plt_section = plt_section_table.add_section(
+            ".plt",
+            SectionKind::Text,
+            PLT_ENTRY_SIZE,
+            vec![0; (symbols.len() + 1) * PLT_ENTRY_SIZE],
+        );
+        let got_plt_section = plt_section_table.add_section(
+            ".got.plt",
+            SectionKind::Data,
+            GOT_PLT_ENTRY_SIZE,
+            vec![0; (symbols.len() + RESERVED_ENTRIES) * GOT_PLT_ENTRY_SIZE],
+        );
+        let section_mapping = section_table.merge(PLT_OBJECT, plt_section_table);
+        let plt = section_mapping.placement(plt_section).unwrap();
+        let got_plt = section_mapping.placement(got_plt_section).unwrap();
+
+        // Retarget the calls
+        let object: Arc<str> = PLT_OBJECT.into();
+        let stubs = symbols
+            .iter()
+            .enumerate()
+            .map(|(i, &symbol)| {
+                let stub = Symbol {
+                    name: symbol_table.get(symbol).name,
+                    value: SymbolValue::Defined(SymbolDefinition {
+                        section: plt.section,
+                        offset: plt.offset + (i + 1) * PLT_ENTRY_SIZE,
+                        size: PLT_ENTRY_SIZE,
+                    }),
+                    binding: SymbolBinding::Local,
+                };
+                (symbol, symbol_table.add_local(stub, &object))
+            })
+            .collect::<HashMap<_, _>>();
+        for reference in references.iter_mut() {
+            if calls(reference) {
+                reference.0.symbol = stubs[&reference.symbol];
+            }
+        }
+
+        Some(Self {
+            plt,
+            got_plt,
+            symbols,
+        })
+    }
+
+    pub fn got_plt_address(&self, section_table: &InMemoryLoadableSectionTable) -> usize {
+        section_table.address(self.got_plt.section) + self.got_plt.offset
+    }
+
+    /// Writes the stubs and the initial `.got.plt` entries, and returns the entries
+    /// that the loader binds.
+    pub fn fill(&self, section_table: &mut InMemoryLoadableSectionTable) -> Vec<DynamicRelocation> {
+        let plt_address = section_table.address(self.plt.section) + self.plt.offset;
+        let got_plt_address = self.got_plt_address(section_table);
+
+        // pushq GOT+8(%rip); jmpq *GOT+16(%rip); nopl 0(%rax)
+        let mut plt = vec![0xff, 0x35];
+        plt.extend(displacement(got_plt_address + 8, plt_address + 6));
+        plt.extend([0xff, 0x25]);
+        plt.extend(displacement(got_plt_address + 16, plt_address + 12));
+        plt.extend([0x0f, 0x1f, 0x40, 0x00]);
+
+        let mut got_plt = vec![0; RESERVED_ENTRIES * GOT_PLT_ENTRY_SIZE];
+        let mut relocations = vec![];
+        for (i, &symbol) in self.symbols.iter().enumerate() {
+            let stub = plt_address + (i + 1) * PLT_ENTRY_SIZE;
+            let entry = got_plt_address + got_plt.len();
+
+            // jmpq *entry(%rip); pushq $i; jmp header
+            plt.extend([0xff, 0x25]);
+            plt.extend(displacement(entry, stub + 6));
+            plt.push(0x68);
+            plt.extend((i as u32).to_le_bytes());
+            plt.push(0xe9);
+            plt.extend(displacement(plt_address, stub + 16));
+
+            got_plt.extend(((stub + 6) as u64).to_le_bytes());
+            relocations.push(DynamicRelocation {
+                address: entry,
+                kind: DynamicRelocationKind::PltEntry(symbol),
+                addend: 0,
+            });
+        }
+
+        for (placement, contents) in [(self.plt, plt), (self.got_plt, got_plt)] {
+            let section = section_table.section_mut(placement.section);
+            let bytes = section.data.bytes_mut().unwrap();
+            bytes[placement.offset..placement.offset + contents.len()].copy_from_slice(&contents);
+        }
+        relocations
+    }
+}
+
+/// The 32-bit displacement from the end of an instruction at `next` to `target`.
+fn displacement(target: usize, next: usize) -> [u8; 4] {
+    (target.wrapping_sub(next) as u32).to_le_bytes()
+}
@@ -0,0 +1,88 @@
+use super::models::Endianness;
+
+/// The offset within an FDE of the address of the function it describes.
+pub const PC_BEGIN: usize = 8;
+
+/// A record of an `.eh_frame` section: a CIE (common information entry), or an FDE
+/// (frame description entry) that describes one function and refers to a CIE.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UnwindRecord {
+    /// The offset of the record within the section.
+    pub offset: usize,
+    /// The size of the record, including its length field.
+    pub size: usize,
+    /// The offset of the CIE of an FDE, or `None` for a CIE.
+    pub cie: Option<usize>,
+}
+impl UnwindRecord {
+    pub fn contains(&self, offset: usize) -> bool {
+        (self.offset..self.offset + self.size).contains(&offset)
+    }
+}
+
+/// Splits the contents of an `.eh_frame` section into records, or returns `None` if
+/// they are malformed or use 64-bit lengths.
+pub fn unwind_records(data: &[u8], endianness: Endianness) -> Option<Vec<UnwindRecord>> {
+    let mut records = vec![];
+    let mut offset = 0;
+    while offset < data.len() {
+        let length = endianness.read(data.get(offset..offset + 4)?);
+        // A zero length terminates the table
+        if length == 0 {
+            records.push(UnwindRecord {
+                offset,
+                size: 4,
+                cie: None,
+            });
+            offset += 4;
+            continue;
+        }
+        if length < 4 || length == 0xffff_ffff {
+            return None;
+        }
+        let size = length + 4;
+        data.get(offset..offset + size)?;
+        // An FDE holds the distance back to its CIE instead of a CIE's 0
+        let cie = match endianness.read(&data[offset + 4..offset + 8]) {
+            0 => None,
+            distance => Some((offset + 4).checked_sub(distance)?),
+        };
+        records.push(UnwindRecord { offset, size, cie });
+        offset += size;
+    }
+    Some(records)
+}
+
+/// Removes the records whose index `keep` rejects from `data`, pointing the
+/// remaining FDEs at the new offsets of their CIEs.
+///
+/// Returns the new offset of each kept record, in order.
+pub fn remove_unwind_records(
+    data: &mut Vec<u8>,
+    records: &[UnwindRecord],
+    endianness: Endianness,
+    keep: impl Fn(usize) -> bool,
+) -> Vec<(UnwindRecord, usize)> {
+    let mut kept = vec![];
+    let mut new_data = Vec::with_capacity(data.len());
+    for (index, record) in records.iter().enumerate() {
+        if keep(index) {
+            kept.push((*record, new_data.len()));
+            new_data.extend_from_slice(&data[record.offset..record.offset + record.size]);
+        }
+    }
+    for &(record, new_offset) in &kept {
+        let Some(cie) = record.cie else {
+            continue;
+        };
+        let Some(&(_, new_cie)) = kept.iter().find(|(record, _)| record.offset == cie) else {
+            continue;
+        };
+        endianness.write(
+            &mut new_data[new_offset + 4..new_offset + 8],
+            new_offset + 4 - new_cie,
+        );
+    }
+    *data = new_data;
+    kept
+}
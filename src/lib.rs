@@ -1,2 +1,3 @@
 pub mod core;
+pub mod elf;
 pub mod in_memory;